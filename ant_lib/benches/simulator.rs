@@ -0,0 +1,21 @@
+#![feature(test)]
+
+extern crate ant_lib;
+extern crate test;
+
+use std::u32;
+
+use ant_lib::Simulator;
+use ant_lib::test_data::{ant1, sample0};
+use test::Bencher;
+
+/// Plays `ant1` against itself on `sample0`, 100 rounds per iteration
+///
+/// The same game continues across iterations, so the measurement covers its different phases.
+/// The amount of rounds per second is 10^11 divided by the reported ns/iter. Run it with
+/// `cargo bench` in `ant_lib`; the readme lists the baseline numbers.
+#[bench]
+fn bench_sample0_ant1(b: &mut Bencher) {
+    let mut simulator = Simulator::new(sample0(), ant1(), ant1(), u32::MAX, 12345);
+    b.iter(|| simulator.run_rounds(100));
+}
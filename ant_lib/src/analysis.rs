@@ -0,0 +1,95 @@
+//! Static checks over ant programs
+//!
+//! The simulator assumes that every state an instruction jumps to exists and that every `Flip`
+//! has a positive chance. Violating those assumptions results in a panic at some point during
+//! the game, so it is better to check them before running it.
+
+use std::fmt;
+
+use ant::AntState;
+use instruction::Instruction;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub state: AntState,
+    pub kind: DiagnosticKind
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    /// The program has no instructions at all
+    Empty,
+    /// The instruction jumps to a state that is not in the program
+    MissingState(AntState),
+    /// The state can't be reached from state 0
+    Unreachable,
+    /// A `Flip 0`, which would result in a division by zero
+    FlipZero
+}
+
+impl Diagnostic {
+    /// Errors make the simulation panic, the rest are only warnings
+    pub fn is_error(&self) -> bool {
+        self.kind != DiagnosticKind::Unreachable
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = if self.is_error() { "error" } else { "warning" };
+        write!(f, "{}: ", severity)?;
+
+        use self::DiagnosticKind::*;
+        match self.kind {
+            Empty => write!(f, "the program has no instructions"),
+            MissingState(target) => write!(f, "state {} jumps to missing state {}", self.state, target),
+            Unreachable => write!(f, "state {} is unreachable", self.state),
+            FlipZero => write!(f, "state {} flips with a chance of 0", self.state)
+        }
+    }
+}
+
+/// Checks the given program, returning the diagnostics sorted by state
+pub fn analyze(instrs: &[Instruction]) -> Vec<Diagnostic> {
+    if instrs.is_empty() {
+        return vec![Diagnostic { state: 0, kind: DiagnosticKind::Empty }];
+    }
+
+    let reachable = reachable_states(instrs);
+    let mut diagnostics = Vec::new();
+    for (state, instr) in instrs.iter().enumerate() {
+        let state = state as AntState;
+        if !reachable[state as usize] {
+            diagnostics.push(Diagnostic { state, kind: DiagnosticKind::Unreachable });
+        }
+
+        if let Instruction::Flip(0, _, _) = *instr {
+            diagnostics.push(Diagnostic { state, kind: DiagnosticKind::FlipZero });
+        }
+
+        for target in instr.next_states() {
+            if target as usize >= instrs.len() {
+                diagnostics.push(Diagnostic { state, kind: DiagnosticKind::MissingState(target) });
+            }
+        }
+    }
+
+    diagnostics.dedup();
+    diagnostics
+}
+
+/// Returns whether each state can be reached from state 0
+pub fn reachable_states(instrs: &[Instruction]) -> Vec<bool> {
+    let mut reachable = vec![false; instrs.len()];
+    let mut pending = vec![0];
+    while let Some(state) = pending.pop() {
+        if state as usize >= instrs.len() || reachable[state as usize] {
+            continue;
+        }
+
+        reachable[state as usize] = true;
+        pending.extend(instrs[state as usize].next_states());
+    }
+
+    reachable
+}
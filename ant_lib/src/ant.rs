@@ -13,6 +13,14 @@ impl AntColor {
             AntColor::Black => AntColor::Red
         }
     }
+
+    /// The name of the color, as written in dumps and snapshots
+    pub fn name(self) -> &'static str {
+        match self {
+            AntColor::Red => "red",
+            AntColor::Black => "black"
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -52,7 +60,7 @@ impl AntDirection {
 }
 
 pub type AntId = u16;
-pub type AntState = u16; // 0..9999
+pub type AntState = u16; // 0..9998
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Ant {
@@ -61,18 +69,23 @@ pub struct Ant {
     pub state: AntState,
     pub resting: u8,
     pub direction: AntDirection, // From 0 to 5, since the world is hexagonal
-    pub has_food: bool
+    pub has_food: bool,
+    /// The index of the cell where the ant is, which is meaningless once it is dead
+    pub position: usize,
+    pub alive: bool
 }
 
 impl Ant {
-    pub fn new(id: AntId, color: AntColor) -> Ant {
+    pub fn new(id: AntId, color: AntColor, position: usize) -> Ant {
         Ant {
             color,
             id,
             state: 0,
             resting: 0,
             direction: AntDirection::Right,
-            has_food: false
+            has_food: false,
+            position,
+            alive: true
         }
     }
 }
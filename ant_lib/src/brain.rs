@@ -0,0 +1,253 @@
+//! Ant programs in the form used by the simulator
+//!
+//! Every living ant runs an instruction each round, so instructions are decoded once, before the
+//! game starts, into `Copy` operations that need no further interpretation. In particular, sense
+//! and turn directions become the amount of steps to rotate the direction of the ant by.
+
+use ant::AntState;
+use instruction::{Condition, InvChance, Instruction, MarkerNumber, SenseDir, TurnDir};
+
+/// The rotation used by `Sense Here`, which doesn't depend on the direction of the ant
+pub const HERE: u8 = 6;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Op {
+    /// Senses the cell in the direction of the ant rotated clockwise by the given amount of steps,
+    /// or the cell of the ant itself if the amount is `HERE`
+    Sense(u8, Condition, AntState, AntState),
+    Mark(MarkerNumber, AntState),
+    Unmark(MarkerNumber, AntState),
+    PickUp(AntState, AntState),
+    Drop(AntState),
+    /// Rotates the ant clockwise by the given amount of steps
+    Turn(u8, AntState),
+    Move(AntState, AntState),
+    Flip(InvChance, AntState, AntState)
+}
+
+impl Op {
+    fn decode(instr: &Instruction) -> Op {
+        match *instr {
+            Instruction::Sense(dir, st1, st2, cond) => Op::Sense(sense_rotation(dir), cond, st1, st2),
+            Instruction::Mark(i, st) => Op::Mark(i, st),
+            Instruction::Unmark(i, st) => Op::Unmark(i, st),
+            Instruction::PickUp(st1, st2) => Op::PickUp(st1, st2),
+            Instruction::Drop(st) => Op::Drop(st),
+            Instruction::Turn(dir, st) => Op::Turn(turn_rotation(dir), st),
+            Instruction::Move(st1, st2) => Op::Move(st1, st2),
+            Instruction::Flip(p, st1, st2) => Op::Flip(p, st1, st2)
+        }
+    }
+}
+
+fn sense_rotation(dir: SenseDir) -> u8 {
+    match dir {
+        SenseDir::Here => HERE,
+        SenseDir::Ahead => 0,
+        SenseDir::LeftAhead => turn_rotation(TurnDir::Left),
+        SenseDir::RightAhead => turn_rotation(TurnDir::Right)
+    }
+}
+
+fn turn_rotation(dir: TurnDir) -> u8 {
+    match dir {
+        TurnDir::Left => 5,
+        TurnDir::Right => 1
+    }
+}
+
+/// A decoded ant program, indexed by state
+pub struct Brain {
+    ops: Vec<Op>
+}
+
+impl Brain {
+    pub fn new(instrs: &[Instruction]) -> Brain {
+        Brain { ops: instrs.iter().map(Op::decode).collect() }
+    }
+
+    pub fn op(&self, state: AntState) -> Op {
+        self.ops[state as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ant::{AntColor, AntDirection, AntId, AntState};
+    use dump;
+    use instruction::{Instruction, SenseDir, TurnDir};
+    use simulator::Simulator;
+    use test_data::{ant1, default_program, sample0};
+    use util::Rng;
+    use world::World;
+    use super::{sense_rotation, turn_rotation, Brain, Op, HERE};
+
+    #[test]
+    fn test_decode() {
+        use instruction::Condition::*;
+        let instrs = [
+            Instruction::Sense(SenseDir::Here, 1, 2, Friend),
+            Instruction::Sense(SenseDir::Ahead, 3, 4, Marker(5)),
+            Instruction::Sense(SenseDir::LeftAhead, 5, 6, FoeHome),
+            Instruction::Sense(SenseDir::RightAhead, 7, 8, FoeWithFood),
+            Instruction::Mark(0, 9),
+            Instruction::Unmark(5, 10),
+            Instruction::PickUp(11, 12),
+            Instruction::Drop(13),
+            Instruction::Turn(TurnDir::Left, 14),
+            Instruction::Turn(TurnDir::Right, 15),
+            Instruction::Move(16, 17),
+            Instruction::Flip(1, 18, 19),
+            Instruction::Flip(65535, 0, 9999)
+        ];
+
+        let brain = Brain::new(&instrs);
+        let ops: Vec<_> = (0..instrs.len()).map(|state| brain.op(state as AntState)).collect();
+        assert_eq!(ops, vec![
+            Op::Sense(HERE, Friend, 1, 2),
+            Op::Sense(0, Marker(5), 3, 4),
+            Op::Sense(5, FoeHome, 5, 6),
+            Op::Sense(1, FoeWithFood, 7, 8),
+            Op::Mark(0, 9),
+            Op::Unmark(5, 10),
+            Op::PickUp(11, 12),
+            Op::Drop(13),
+            Op::Turn(5, 14),
+            Op::Turn(1, 15),
+            Op::Move(16, 17),
+            Op::Flip(1, 18, 19),
+            Op::Flip(65535, 0, 9999)
+        ]);
+    }
+
+    #[test]
+    fn test_rotations() {
+        // Rotating by a decoded turn is the same as turning, from any direction
+        for dir in AntDirection::all() {
+            for &turn in &[TurnDir::Left, TurnDir::Right] {
+                assert_eq!(AntDirection::from_u8(dir as u8 + turn_rotation(turn)), dir.turn(turn));
+            }
+
+            assert_eq!(AntDirection::from_u8(dir as u8 + sense_rotation(SenseDir::Ahead)), dir);
+            assert_eq!(AntDirection::from_u8(dir as u8 + sense_rotation(SenseDir::LeftAhead)), dir.turn(TurnDir::Left));
+            assert_eq!(AntDirection::from_u8(dir as u8 + sense_rotation(SenseDir::RightAhead)), dir.turn(TurnDir::Right));
+        }
+    }
+
+    /// Runs a round by interpreting the instructions directly, like the simulator did before brains
+    /// were decoded
+    fn interpret_round(world: &mut World, red: &[Instruction], black: &[Instruction], rng: &mut Rng) {
+        for id in 0..world.ants.len() {
+            let ant = world.ants[id].clone();
+            if !ant.alive {
+                continue;
+            }
+
+            if ant.resting > 0 {
+                world.ants[id].resting -= 1;
+                continue;
+            }
+
+            let instrs = match ant.color {
+                AntColor::Red => red,
+                AntColor::Black => black
+            };
+
+            let (width, pos) = (world.width, ant.position);
+            let new_state = match instrs[ant.state as usize] {
+                Instruction::Sense(dir, st1, st2, cond) => {
+                    let sensed = match dir {
+                        SenseDir::Here => pos,
+                        SenseDir::Ahead => World::adjacent_position(width, pos, ant.direction),
+                        SenseDir::LeftAhead => World::adjacent_position(width, pos, ant.direction.turn(TurnDir::Left)),
+                        SenseDir::RightAhead => World::adjacent_position(width, pos, ant.direction.turn(TurnDir::Right))
+                    };
+
+                    if cond.eval(world, sensed, ant.color) { st1 } else { st2 }
+                }
+                Instruction::Mark(mark, st) => {
+                    world.cells[pos].markers_mut(ant.color).set_bit(mark);
+                    st
+                }
+                Instruction::Unmark(mark, st) => {
+                    world.cells[pos].markers_mut(ant.color).clear(mark);
+                    st
+                }
+                Instruction::PickUp(st1, st2) => {
+                    if ant.has_food || world.cells[pos].food == 0 {
+                        st2
+                    } else {
+                        world.cells[pos].food -= 1;
+                        world.ants[id].has_food = true;
+                        st1
+                    }
+                }
+                Instruction::Drop(st) => {
+                    if ant.has_food {
+                        world.cells[pos].food += 1;
+                        world.ants[id].has_food = false;
+                    }
+
+                    st
+                }
+                Instruction::Turn(dir, st) => {
+                    world.ants[id].direction = ant.direction.turn(dir);
+                    st
+                }
+                Instruction::Move(st1, st2) => {
+                    let target = World::adjacent_position(width, pos, ant.direction);
+                    if world.cells[target].is_rocky || world.cells[target].ant.is_some() {
+                        st2
+                    } else {
+                        world.cells[pos].ant = None;
+                        world.cells[target].ant = Some(id as AntId);
+                        world.ants[id].position = target;
+                        world.ants[id].resting = 14;
+
+                        let adjacent = AntDirection::all().map(|dir| World::adjacent_position(width, target, dir));
+                        for position in Some(target).into_iter().chain(adjacent) {
+                            let surrounded = world.ant_at(position).map(|a| world.adjacent_enemies(position, a.color) >= 5);
+                            if surrounded == Some(true) {
+                                let dead = world.cells[position].ant.take().unwrap() as usize;
+                                world.ants[dead].alive = false;
+                                let food = if world.ants[dead].has_food { 4 } else { 3 };
+                                world.cells[position].food += food;
+                            }
+                        }
+
+                        st1
+                    }
+                }
+                Instruction::Flip(p, st1, st2) => {
+                    if rng.random_int(p as usize) == 0 { st1 } else { st2 }
+                }
+            };
+
+            world.ants[id].state = new_state;
+        }
+    }
+
+    #[test]
+    fn test_same_dump_as_instructions() {
+        let (red, black) = (ant1(), default_program());
+        let mut simulator = Simulator::new(sample0(), red.clone(), black.clone(), 2000, 42);
+        let mut world = sample0();
+        world.populate();
+        let mut rng = Rng::new(42);
+
+        for round in 1...2000 {
+            simulator.one_round();
+            interpret_round(&mut world, &red, &black, &mut rng);
+
+            if round % 100 == 0 {
+                let (mut expected, mut actual) = (Vec::new(), Vec::new());
+                dump::write_round(&mut expected, &world, round).unwrap();
+                simulator.dump(&mut actual).unwrap();
+                assert!(actual == expected, "the dumps differ after round {}", round);
+            }
+        }
+
+        // Some ants were killed along the way, so fights were compared too
+        assert!(world.ants.iter().any(|ant| !ant.alive));
+    }
+}
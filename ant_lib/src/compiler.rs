@@ -0,0 +1,820 @@
+//! A compiler from a small structured language to ant instructions
+//!
+//! Writing brains directly in the assembly accepted by `Instruction::parse` means keeping track
+//! of thousands of state numbers by hand. This module compiles a structured language instead:
+//!
+//! ```text
+//! ; Turns randomly or moves ahead
+//! proc wander {
+//!     loop {
+//!         if random(3) { turn left break }
+//!         else if random(2) { turn right break }
+//!         else if move { break }
+//!     }
+//! }
+//!
+//! main {
+//!   search:
+//!     for $dir in leftahead, rightahead {
+//!         if sense $dir food { turn $dir goto search }
+//!     }
+//!     if sense ahead food and move and pickup {
+//!         mark 0
+//!     }
+//!     wander
+//! }
+//! ```
+//!
+//! A program consists of procedures and a single `main` block. When the end of `main` is reached,
+//! execution starts again at its beginning. The following statements are available:
+//!
+//! * `mark N`, `unmark N`, `drop`, `turn left`, `turn right`, `move` and `pickup`. When used as a
+//!   statement, a failing `move` or `pickup` is ignored.
+//! * `if COND { ... }`, optionally followed by `else { ... }` or `else if ...`.
+//! * `while COND { ... }` and `loop { ... }`, together with `break` and `continue`.
+//! * `name:` to define a label and `goto name` to jump to it.
+//! * `for $var in a, b, c { ... }`, which repeats the body once per value, replacing `$var` by it.
+//!   This is typically used to expand the same code for several sense directions.
+//! * `name` or `name(a, b)` to call a procedure declared as `proc name($x, $y) { ... }`.
+//!   Procedures are expanded at each call, like macros, so each call results in new states.
+//!   Recursion is not allowed.
+//!
+//! Conditions are `sense DIR COND` (with the same directions and conditions as the `Sense`
+//! instruction), `move` and `pickup` (which are true when the action succeeds) and `random(N)`
+//! (which is true with a chance of 1 / N). They can be combined with `not`, `and`, `or` and
+//! parentheses. Everything after a `;` is a comment.
+//!
+//! Labels are local to the procedure call or `for` iteration that defines them, but a `goto` can
+//! jump to a label of an enclosing scope.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use ant::AntState;
+use instruction::{Condition, InvChance, Instruction, MarkerNumber, SenseDir, TurnDir, MAX_STATES};
+
+const KEYWORDS: &'static [&'static str] = &[
+    "and", "break", "continue", "drop", "else", "for", "goto", "if", "in", "loop", "main", "mark",
+    "move", "not", "or", "pickup", "proc", "random", "sense", "turn", "unmark", "while"
+];
+
+/// Compiles the given source to a list of instructions, starting at state 0
+pub fn compile(source: &str) -> Result<Vec<Instruction>, CompileError> {
+    let tokens = tokenize(source)?;
+    let mut input = Tokens::new(tokens);
+
+    // First collect the procedures, so they can be called before their declaration
+    let mut procs = HashMap::new();
+    let mut main = None;
+    while input.peek().is_some() {
+        let t = input.next()?;
+        match &t.text[..] {
+            "proc" => {
+                let name = input.name()?;
+                let mut params = Vec::new();
+                if input.eat("(") && !input.eat(")") {
+                    loop {
+                        params.push(input.variable()?.text);
+                        if input.eat(")") {
+                            break;
+                        }
+                        input.expect(",")?;
+                    }
+                }
+
+                let (body, end) = input.raw_block()?;
+                if procs.contains_key(&name.text) {
+                    return Err(name.error(CompileErrorKind::DuplicateProcedure));
+                }
+
+                procs.insert(name.text, Procedure { params, body, end });
+            }
+            "main" => {
+                if main.is_some() {
+                    return Err(t.error(CompileErrorKind::DuplicateProcedure));
+                }
+
+                main = Some((t.pos, input.raw_block()?));
+            }
+            _ => return Err(t.error(CompileErrorKind::UnexpectedToken))
+        }
+    }
+
+    let (pos, (body, end)) = match main {
+        Some(main) => main,
+        None => return Err(CompileError::new(input.end.pos, "", CompileErrorKind::MissingMain))
+    };
+
+    let mut calls = Vec::new();
+    let stmts = parse_stmts(body, end, &procs, &mut calls)?;
+
+    let mut compiler = Compiler { nodes: Vec::new(), scopes: Vec::new(), main: pos };
+    let start = compiler.push(Node::Jump(None, pos))?;
+    let entry = compiler.scope(&stmts, start, None)?;
+    compiler.nodes[start] = Node::Jump(Some(entry), pos);
+    compiler.emit(start)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompileError {
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub kind: CompileErrorKind
+}
+
+impl CompileError {
+    fn new(pos: Pos, token: &str, kind: CompileErrorKind) -> CompileError {
+        CompileError { line: pos.line, column: pos.column, token: token.to_string(), kind }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind.description())?;
+        if !self.token.is_empty() {
+            write!(f, " `{}`", self.token)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for CompileError {
+    fn description(&self) -> &str {
+        self.kind.description()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompileErrorKind {
+    UnexpectedCharacter,
+    UnexpectedToken,
+    UnexpectedEnd,
+    UnboundVariable,
+    InvalidMarker,
+    InvalidChance,
+    InvalidSenseDir,
+    InvalidTurnDir,
+    InvalidCondition,
+    MissingMain,
+    DuplicateProcedure,
+    UnknownProcedure,
+    WrongArity,
+    RecursiveProcedure,
+    DuplicateLabel,
+    UndefinedLabel,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    EmptyLoop,
+    TooManyStates
+}
+
+impl CompileErrorKind {
+    fn description(self) -> &'static str {
+        use self::CompileErrorKind::*;
+        match self {
+            UnexpectedCharacter => "unexpected character",
+            UnexpectedToken     => "unexpected token",
+            UnexpectedEnd       => "unexpected end of the program after",
+            UnboundVariable     => "unbound variable",
+            InvalidMarker       => "invalid marker (expected a number between 0 and 5)",
+            InvalidChance       => "invalid random chance (expected a positive number)",
+            InvalidSenseDir     => "invalid sense direction",
+            InvalidTurnDir      => "invalid turn direction",
+            InvalidCondition    => "invalid condition",
+            MissingMain         => "the program has no main block",
+            DuplicateProcedure  => "duplicate procedure",
+            UnknownProcedure    => "unknown procedure",
+            WrongArity          => "wrong number of arguments for procedure",
+            RecursiveProcedure  => "recursive call to procedure",
+            DuplicateLabel      => "duplicate label",
+            UndefinedLabel      => "undefined label",
+            BreakOutsideLoop    => "break outside of a loop",
+            ContinueOutsideLoop => "continue outside of a loop",
+            EmptyLoop           => "loop without any instructions",
+            TooManyStates       => "too many states (the maximum is 9999) in"
+        }
+    }
+}
+
+/// A (1-indexed) position in the source
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Pos {
+    line: usize,
+    column: usize
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    pos: Pos,
+    text: String
+}
+
+impl Token {
+    fn error(&self, kind: CompileErrorKind) -> CompileError {
+        // Leftover variables are a common mistake, so they get a more helpful error
+        let kind = match kind {
+            CompileErrorKind::UnexpectedToken
+            | CompileErrorKind::InvalidMarker
+            | CompileErrorKind::InvalidChance
+            | CompileErrorKind::InvalidSenseDir
+            | CompileErrorKind::InvalidTurnDir
+            | CompileErrorKind::InvalidCondition if self.text.starts_with('$') => CompileErrorKind::UnboundVariable,
+            kind => kind
+        };
+
+        CompileError::new(self.pos, &self.text, kind)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_name(text: &str) -> bool {
+    text.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_')
+        && text.chars().all(is_word_char)
+        && !KEYWORDS.contains(&text)
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, CompileError> {
+    let mut tokens = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        // Discard comments
+        let line = line.split(';').next().unwrap_or("");
+
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            let pos = Pos { line: i + 1, column: line[..start].chars().count() + 1 };
+            let end = if c.is_whitespace() {
+                continue;
+            } else if "{}(),:".contains(c) {
+                start + c.len_utf8()
+            } else if is_word_char(c) || c == '$' {
+                let mut end = start + c.len_utf8();
+                while let Some(&(j, d)) = chars.peek() {
+                    if !is_word_char(d) {
+                        break;
+                    }
+
+                    end = j + d.len_utf8();
+                    chars.next();
+                }
+
+                end
+            } else {
+                return Err(CompileError::new(pos, &line[start..start + c.len_utf8()], CompileErrorKind::UnexpectedCharacter));
+            };
+
+            tokens.push(Token { pos, text: line[start..end].to_string() });
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// The tokens of a block, with a cursor
+struct Tokens {
+    tokens: Vec<Token>,
+    position: usize,
+    /// The token after which the block ends, for error reporting
+    end: Token
+}
+
+impl Tokens {
+    fn new(tokens: Vec<Token>) -> Tokens {
+        let end = tokens.last().cloned().unwrap_or(Token { pos: Pos { line: 1, column: 1 }, text: String::new() });
+        Tokens::with_end(tokens, end)
+    }
+
+    fn with_end(tokens: Vec<Token>, end: Token) -> Tokens {
+        Tokens { tokens, position: 0, end }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, CompileError> {
+        match self.tokens.get(self.position).cloned() {
+            Some(t) => {
+                self.position += 1;
+                Ok(t)
+            }
+            None => Err(self.end.error(CompileErrorKind::UnexpectedEnd))
+        }
+    }
+
+    /// Skips the next token if it is `text`
+    fn eat(&mut self, text: &str) -> bool {
+        let found = self.peek().map_or(false, |t| t.text == text);
+        if found {
+            self.position += 1;
+        }
+
+        found
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, CompileError> {
+        let t = self.next()?;
+        if t.text == text {
+            Ok(t)
+        } else {
+            Err(t.error(CompileErrorKind::UnexpectedToken))
+        }
+    }
+
+    /// A label or procedure name
+    fn name(&mut self) -> Result<Token, CompileError> {
+        let t = self.next()?;
+        if is_name(&t.text) {
+            Ok(t)
+        } else {
+            Err(t.error(CompileErrorKind::UnexpectedToken))
+        }
+    }
+
+    /// A `$` followed by a name
+    fn variable(&mut self) -> Result<Token, CompileError> {
+        let t = self.next()?;
+        if t.text.starts_with('$') && is_name(&t.text[1..]) {
+            Ok(t)
+        } else {
+            Err(CompileError::new(t.pos, &t.text, CompileErrorKind::UnexpectedToken))
+        }
+    }
+
+    /// A word that can be substituted for a variable
+    fn argument(&mut self) -> Result<Token, CompileError> {
+        let t = self.next()?;
+        if t.text.chars().all(|c| is_word_char(c) || c == '$') {
+            Ok(t)
+        } else {
+            Err(t.error(CompileErrorKind::UnexpectedToken))
+        }
+    }
+
+    /// The tokens between a pair of braces, together with the closing brace
+    fn raw_block(&mut self) -> Result<(Vec<Token>, Token), CompileError> {
+        self.expect("{")?;
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        loop {
+            let t = self.next()?;
+            match &t.text[..] {
+                "{" => depth += 1,
+                "}" if depth == 0 => return Ok((tokens, t)),
+                "}" => depth -= 1,
+                _ => ()
+            }
+
+            tokens.push(t);
+        }
+    }
+}
+
+struct Procedure {
+    params: Vec<String>,
+    body: Vec<Token>,
+    end: Token
+}
+
+/// Replaces each variable by its value
+fn substitute(tokens: &[Token], bindings: &[(&str, &Token)]) -> Vec<Token> {
+    tokens.iter().map(|t| {
+        bindings.iter()
+                .filter(|&&(var, _)| var == t.text)
+                .map(|&(_, value)| value.clone())
+                .next()
+                .unwrap_or_else(|| t.clone())
+    }).collect()
+}
+
+enum Cond {
+    Sense(SenseDir, Condition),
+    Move,
+    PickUp,
+    Random(InvChance),
+    Not(Box<Cond>),
+    And(Box<Cond>, Box<Cond>),
+    Or(Box<Cond>, Box<Cond>)
+}
+
+enum Stmt {
+    Mark(MarkerNumber),
+    Unmark(MarkerNumber),
+    Drop,
+    Turn(TurnDir),
+    Move,
+    PickUp,
+    Label(String, Pos),
+    Goto(String, Pos),
+    Break(Pos),
+    Continue(Pos),
+    If(Cond, Vec<Stmt>, Vec<Stmt>),
+    While(Cond, Vec<Stmt>, Pos),
+    Loop(Vec<Stmt>, Pos),
+    /// Statements with their own labels
+    Scope(Vec<Stmt>),
+    /// Statements sharing the labels of the enclosing scope
+    Seq(Vec<Stmt>)
+}
+
+fn parse_stmts(tokens: Vec<Token>, end: Token, procs: &HashMap<String, Procedure>, calls: &mut Vec<String>) -> Result<Vec<Stmt>, CompileError> {
+    let mut parser = Parser { input: Tokens::with_end(tokens, end), procs, calls };
+    let mut stmts = Vec::new();
+    while parser.input.peek().is_some() {
+        stmts.push(parser.stmt()?);
+    }
+
+    Ok(stmts)
+}
+
+struct Parser<'p> {
+    input: Tokens,
+    procs: &'p HashMap<String, Procedure>,
+    /// The procedures being expanded, to detect recursion
+    calls: &'p mut Vec<String>
+}
+
+impl<'p> Parser<'p> {
+    fn stmt(&mut self) -> Result<Stmt, CompileError> {
+        let t = self.input.next()?;
+        match &t.text[..] {
+            "mark" => Ok(Stmt::Mark(self.marker()?)),
+            "unmark" => Ok(Stmt::Unmark(self.marker()?)),
+            "drop" => Ok(Stmt::Drop),
+            "turn" => {
+                let dir = self.input.next()?;
+                TurnDir::from_name(&dir.text).map(Stmt::Turn).ok_or_else(|| dir.error(CompileErrorKind::InvalidTurnDir))
+            }
+            "move" => Ok(Stmt::Move),
+            "pickup" => Ok(Stmt::PickUp),
+            "if" => self.if_stmt(),
+            "while" => {
+                let cond = self.cond()?;
+                Ok(Stmt::While(cond, self.block()?, t.pos))
+            }
+            "loop" => Ok(Stmt::Loop(self.block()?, t.pos)),
+            "break" => Ok(Stmt::Break(t.pos)),
+            "continue" => Ok(Stmt::Continue(t.pos)),
+            "goto" => {
+                let label = self.input.name()?;
+                Ok(Stmt::Goto(label.text, label.pos))
+            }
+            "for" => self.for_stmt(),
+            _ if is_name(&t.text) => {
+                if self.input.eat(":") {
+                    Ok(Stmt::Label(t.text, t.pos))
+                } else {
+                    self.call(t)
+                }
+            }
+            _ => Err(t.error(CompileErrorKind::UnexpectedToken))
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.input.expect("{")?;
+        let mut stmts = Vec::new();
+        while !self.input.eat("}") {
+            stmts.push(self.stmt()?);
+        }
+
+        Ok(stmts)
+    }
+
+    fn if_stmt(&mut self) -> Result<Stmt, CompileError> {
+        let cond = self.cond()?;
+        let then = self.block()?;
+        let otherwise = if !self.input.eat("else") {
+            Vec::new()
+        } else if self.input.eat("if") {
+            vec![self.if_stmt()?]
+        } else {
+            self.block()?
+        };
+
+        Ok(Stmt::If(cond, then, otherwise))
+    }
+
+    fn for_stmt(&mut self) -> Result<Stmt, CompileError> {
+        let var = self.input.variable()?;
+        self.input.expect("in")?;
+        let mut values = vec![self.input.argument()?];
+        while self.input.eat(",") {
+            values.push(self.input.argument()?);
+        }
+
+        let (body, end) = self.input.raw_block()?;
+        let mut iterations = Vec::new();
+        for value in &values {
+            let body = substitute(&body, &[(&var.text[..], value)]);
+            iterations.push(Stmt::Scope(parse_stmts(body, end.clone(), self.procs, self.calls)?));
+        }
+
+        Ok(Stmt::Seq(iterations))
+    }
+
+    fn call(&mut self, name: Token) -> Result<Stmt, CompileError> {
+        let procs = self.procs;
+        let procedure = match procs.get(&name.text) {
+            Some(procedure) => procedure,
+            None => return Err(name.error(CompileErrorKind::UnknownProcedure))
+        };
+
+        let mut args = Vec::new();
+        if self.input.eat("(") && !self.input.eat(")") {
+            loop {
+                args.push(self.input.argument()?);
+                if self.input.eat(")") {
+                    break;
+                }
+                self.input.expect(",")?;
+            }
+        }
+
+        if args.len() != procedure.params.len() {
+            return Err(name.error(CompileErrorKind::WrongArity));
+        }
+
+        if self.calls.contains(&name.text) {
+            return Err(name.error(CompileErrorKind::RecursiveProcedure));
+        }
+
+        let bindings: Vec<_> = procedure.params.iter().map(|p| &p[..]).zip(&args).collect();
+        let body = substitute(&procedure.body, &bindings);
+        self.calls.push(name.text);
+        let stmts = parse_stmts(body, procedure.end.clone(), procs, self.calls)?;
+        self.calls.pop();
+        Ok(Stmt::Scope(stmts))
+    }
+
+    fn marker(&mut self) -> Result<MarkerNumber, CompileError> {
+        let t = self.input.next()?;
+        t.text.parse().into_iter().filter(|&i| i < 6).next().ok_or_else(|| t.error(CompileErrorKind::InvalidMarker))
+    }
+
+    fn cond(&mut self) -> Result<Cond, CompileError> {
+        let mut cond = self.and_cond()?;
+        while self.input.eat("or") {
+            cond = Cond::Or(Box::new(cond), Box::new(self.and_cond()?));
+        }
+
+        Ok(cond)
+    }
+
+    fn and_cond(&mut self) -> Result<Cond, CompileError> {
+        let mut cond = self.unary_cond()?;
+        while self.input.eat("and") {
+            cond = Cond::And(Box::new(cond), Box::new(self.unary_cond()?));
+        }
+
+        Ok(cond)
+    }
+
+    fn unary_cond(&mut self) -> Result<Cond, CompileError> {
+        if self.input.eat("not") {
+            return Ok(Cond::Not(Box::new(self.unary_cond()?)));
+        }
+
+        if self.input.eat("(") {
+            let cond = self.cond()?;
+            self.input.expect(")")?;
+            return Ok(cond);
+        }
+
+        let t = self.input.next()?;
+        match &t.text[..] {
+            "sense" => {
+                let dir = self.input.next()?;
+                let dir = SenseDir::from_name(&dir.text).ok_or_else(|| dir.error(CompileErrorKind::InvalidSenseDir))?;
+                let cond = self.input.next()?;
+                let cond = if cond.text.to_uppercase() == "MARKER" {
+                    Condition::Marker(self.marker()?)
+                } else {
+                    Condition::from_name(&cond.text).ok_or_else(|| cond.error(CompileErrorKind::InvalidCondition))?
+                };
+
+                Ok(Cond::Sense(dir, cond))
+            }
+            "move" => Ok(Cond::Move),
+            "pickup" => Ok(Cond::PickUp),
+            "random" => {
+                self.input.expect("(")?;
+                let n = self.input.next()?;
+                let chance = n.text.parse().into_iter().filter(|&c| c > 0).next().ok_or_else(|| n.error(CompileErrorKind::InvalidChance))?;
+                self.input.expect(")")?;
+                Ok(Cond::Random(chance))
+            }
+            _ => Err(t.error(CompileErrorKind::UnexpectedToken))
+        }
+    }
+}
+
+/// The program is compiled to a graph of nodes, identified by their index. Jumps are removed when
+/// emitting the final instructions.
+enum Node {
+    /// An instruction, with node indices instead of states
+    Instr(Instruction),
+    /// A jump to another node. The target is `None` while it is not known yet.
+    Jump(Option<usize>, Pos)
+}
+
+#[derive(Clone, Copy)]
+struct LoopExits {
+    /// The target of a `break`
+    exit: usize,
+    /// The target of a `continue`
+    head: usize
+}
+
+/// Compiles statements backwards: each statement is compiled knowing the node that follows it
+struct Compiler {
+    nodes: Vec<Node>,
+    scopes: Vec<HashMap<String, usize>>,
+    main: Pos
+}
+
+impl Compiler {
+    fn push(&mut self, node: Node) -> Result<usize, CompileError> {
+        // Node indices are stored as states in the instructions
+        if self.nodes.len() > AntState::max_value() as usize {
+            return Err(CompileError::new(self.main, "main", CompileErrorKind::TooManyStates));
+        }
+
+        self.nodes.push(node);
+        Ok(self.nodes.len() - 1)
+    }
+
+    fn scope(&mut self, stmts: &[Stmt], next: usize, exits: Option<LoopExits>) -> Result<usize, CompileError> {
+        let mut labels = HashMap::new();
+        self.declare_labels(stmts, &mut labels)?;
+        self.scopes.push(labels);
+        let entry = self.block(stmts, next, exits);
+        self.scopes.pop();
+        entry
+    }
+
+    fn declare_labels(&mut self, stmts: &[Stmt], labels: &mut HashMap<String, usize>) -> Result<(), CompileError> {
+        for stmt in stmts {
+            match *stmt {
+                Stmt::Label(ref name, pos) => {
+                    if labels.contains_key(name) {
+                        return Err(CompileError::new(pos, name, CompileErrorKind::DuplicateLabel));
+                    }
+
+                    let node = self.push(Node::Jump(None, pos))?;
+                    labels.insert(name.clone(), node);
+                }
+                Stmt::If(_, ref then, ref otherwise) => {
+                    self.declare_labels(then, labels)?;
+                    self.declare_labels(otherwise, labels)?;
+                }
+                Stmt::While(_, ref stmts, _) | Stmt::Loop(ref stmts, _) | Stmt::Seq(ref stmts) => {
+                    self.declare_labels(stmts, labels)?;
+                }
+                _ => ()
+            }
+        }
+
+        Ok(())
+    }
+
+    fn label(&self, name: &str, pos: Pos) -> Result<usize, CompileError> {
+        self.scopes.iter()
+                   .rev()
+                   .filter_map(|labels| labels.get(name))
+                   .next()
+                   .cloned()
+                   .ok_or_else(|| CompileError::new(pos, name, CompileErrorKind::UndefinedLabel))
+    }
+
+    fn block(&mut self, stmts: &[Stmt], mut next: usize, exits: Option<LoopExits>) -> Result<usize, CompileError> {
+        for stmt in stmts.iter().rev() {
+            next = self.stmt(stmt, next, exits)?;
+        }
+
+        Ok(next)
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, next: usize, exits: Option<LoopExits>) -> Result<usize, CompileError> {
+        let n = next as AntState;
+        match *stmt {
+            Stmt::Mark(i) => self.push(Node::Instr(Instruction::Mark(i, n))),
+            Stmt::Unmark(i) => self.push(Node::Instr(Instruction::Unmark(i, n))),
+            Stmt::Drop => self.push(Node::Instr(Instruction::Drop(n))),
+            Stmt::Turn(dir) => self.push(Node::Instr(Instruction::Turn(dir, n))),
+            Stmt::Move => self.push(Node::Instr(Instruction::Move(n, n))),
+            Stmt::PickUp => self.push(Node::Instr(Instruction::PickUp(n, n))),
+            Stmt::Label(ref name, pos) => {
+                let node = self.label(name, pos)?;
+                self.nodes[node] = Node::Jump(Some(next), pos);
+                Ok(node)
+            }
+            Stmt::Goto(ref name, pos) => self.label(name, pos),
+            Stmt::Break(pos) => {
+                exits.map(|e| e.exit).ok_or_else(|| CompileError::new(pos, "break", CompileErrorKind::BreakOutsideLoop))
+            }
+            Stmt::Continue(pos) => {
+                exits.map(|e| e.head).ok_or_else(|| CompileError::new(pos, "continue", CompileErrorKind::ContinueOutsideLoop))
+            }
+            Stmt::If(ref cond, ref then, ref otherwise) => {
+                let then = self.block(then, next, exits)?;
+                let otherwise = self.block(otherwise, next, exits)?;
+                self.cond(cond, then, otherwise)
+            }
+            Stmt::While(ref cond, ref body, pos) => {
+                let head = self.push(Node::Jump(None, pos))?;
+                let body = self.block(body, head, Some(LoopExits { exit: next, head }))?;
+                let test = self.cond(cond, body, next)?;
+                self.nodes[head] = Node::Jump(Some(test), pos);
+                Ok(head)
+            }
+            Stmt::Loop(ref body, pos) => {
+                let head = self.push(Node::Jump(None, pos))?;
+                let body = self.block(body, head, Some(LoopExits { exit: next, head }))?;
+                self.nodes[head] = Node::Jump(Some(body), pos);
+                Ok(head)
+            }
+            Stmt::Scope(ref stmts) => self.scope(stmts, next, exits),
+            Stmt::Seq(ref stmts) => self.block(stmts, next, exits)
+        }
+    }
+
+    /// Compiles a condition that continues at `t` if it holds and at `f` otherwise
+    fn cond(&mut self, cond: &Cond, t: usize, f: usize) -> Result<usize, CompileError> {
+        let (ts, fs) = (t as AntState, f as AntState);
+        match *cond {
+            Cond::Sense(dir, cond) => self.push(Node::Instr(Instruction::Sense(dir, ts, fs, cond))),
+            Cond::Move => self.push(Node::Instr(Instruction::Move(ts, fs))),
+            Cond::PickUp => self.push(Node::Instr(Instruction::PickUp(ts, fs))),
+            Cond::Random(chance) => self.push(Node::Instr(Instruction::Flip(chance, ts, fs))),
+            Cond::Not(ref cond) => self.cond(cond, f, t),
+            Cond::And(ref a, ref b) => {
+                let b = self.cond(b, t, f)?;
+                self.cond(a, b, f)
+            }
+            Cond::Or(ref a, ref b) => {
+                let b = self.cond(b, t, f)?;
+                self.cond(a, t, b)
+            }
+        }
+    }
+
+    /// Follows jumps until reaching an instruction
+    fn resolve(&self, mut node: usize) -> Result<usize, CompileError> {
+        for _ in 0..self.nodes.len() {
+            match self.nodes[node] {
+                Node::Instr(_) => return Ok(node),
+                Node::Jump(Some(target), _) => node = target,
+                Node::Jump(None, _) => unreachable!("jump target was never set")
+            }
+        }
+
+        // There are more jumps than nodes, so the jumps form a cycle
+        match self.nodes[node] {
+            Node::Jump(_, pos) => Err(CompileError::new(pos, "", CompileErrorKind::EmptyLoop)),
+            Node::Instr(_) => unreachable!()
+        }
+    }
+
+    fn instruction(&self, node: usize) -> &Instruction {
+        match self.nodes[node] {
+            Node::Instr(ref instr) => instr,
+            Node::Jump(..) => unreachable!("jumps are resolved before emitting")
+        }
+    }
+
+    /// Numbers the instructions reachable from `entry` in breadth-first order, so `entry` becomes
+    /// state 0 and unreachable code is left out
+    fn emit(&self, entry: usize) -> Result<Vec<Instruction>, CompileError> {
+        let entry = self.resolve(entry)?;
+        let mut states = HashMap::new();
+        states.insert(entry, 0);
+        let mut order = vec![entry];
+        let mut i = 0;
+        while i < order.len() {
+            for target in self.instruction(order[i]).next_states() {
+                let target = self.resolve(target as usize)?;
+                if !states.contains_key(&target) {
+                    if order.len() == MAX_STATES {
+                        return Err(CompileError::new(self.main, "main", CompileErrorKind::TooManyStates));
+                    }
+
+                    states.insert(target, order.len() as AntState);
+                    order.push(target);
+                }
+            }
+
+            i += 1;
+        }
+
+        // Resolving can't fail anymore, since every target was resolved above
+        Ok(order.iter().map(|&node| {
+            self.instruction(node).map_states(|target| states[&self.resolve(target as usize).unwrap()])
+        }).collect())
+    }
+}
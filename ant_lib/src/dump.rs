@@ -0,0 +1,154 @@
+//! Round-by-round dumps of a game, in the format of the ICFP 2004 reference simulator
+//!
+//! The contest organizers distributed dumps (e.g. `dump.all`) of the state of every cell after
+//! each round, which can be used to check that a simulator behaves exactly like theirs. Each dump
+//! starts with a `random seed: N` line and is followed by one block per round:
+//!
+//! ```text
+//! After round 1...
+//! cell (0, 0): rock
+//! cell (1, 1): 3 food; red hill; red marks: 04; red ant of id 0, dir 1, food 0, state 3, resting 0
+//! ```
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use ant::AntColor;
+use simulator::Simulator;
+use world::World;
+
+/// Writes the line that starts a dump
+pub fn write_header<W: Write>(out: &mut W, seed: u32) -> io::Result<()> {
+    writeln!(out, "random seed: {}", seed)
+}
+
+/// Writes the contents of every cell of the world, preceded by the round number
+pub fn write_round<W: Write>(out: &mut W, world: &World, round: u32) -> io::Result<()> {
+    writeln!(out, "")?;
+    writeln!(out, "After round {}...", round)?;
+    for i in 0..world.cells.len() {
+        write_cell(out, world, i)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a single line describing the cell at the given index
+pub fn write_cell<W: Write>(out: &mut W, world: &World, i: usize) -> io::Result<()> {
+    let (x, y) = World::index_to_coords(world.width, i);
+    let cell = &world.cells[i];
+    write!(out, "cell ({}, {}): ", x, y)?;
+
+    if cell.is_rocky {
+        return writeln!(out, "rock");
+    }
+
+    if cell.food > 0 {
+        write!(out, "{} food; ", cell.food)?;
+    }
+
+    if let Some(color) = cell.anthill {
+        write!(out, "{} hill; ", color.name())?;
+    }
+
+    for &color in &[AntColor::Red, AntColor::Black] {
+        let markers = cell.markers(color);
+        if markers.any() {
+            write!(out, "{} marks: ", color.name())?;
+            for marker in markers.iter() {
+                write!(out, "{}", marker)?;
+            }
+
+            write!(out, "; ")?;
+        }
+    }
+
+    if let Some(ant) = world.ant_at(i) {
+        write!(out, "{} ant of id {}, dir {}, food {}, state {}, resting {}",
+               ant.color.name(),
+               ant.id,
+               ant.direction as u8,
+               ant.has_food as u8,
+               ant.state,
+               ant.resting)?;
+    }
+
+    writeln!(out, "")
+}
+
+/// Runs the simulator until its last round, dumping the world before the first round and after
+/// each of the others
+///
+/// The header has the seed that the game started with, which a restored game keeps from its
+/// snapshot.
+pub fn dump_game<W: Write>(out: &mut W, simulator: &mut Simulator) -> io::Result<()> {
+    write_header(out, simulator.seed)?;
+    simulator.dump(out)?;
+    while simulator.round < simulator.max_rounds {
+        simulator.one_round();
+        simulator.dump(out)?;
+    }
+
+    Ok(())
+}
+
+/// The first difference between a reference dump and the output of our simulator
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mismatch {
+    /// The line of the reference dump (1-indexed)
+    pub line: usize,
+    pub round: u32,
+    pub expected: String,
+    /// The line produced by our simulator, or `None` if the reference dump is longer than ours
+    pub found: Option<String>
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "dump mismatch at line {} (round {})", self.line, self.round)?;
+        writeln!(f, "expected: {}", self.expected)?;
+        match self.found {
+            Some(ref found) => write!(f, "found:    {}", found),
+            None => write!(f, "found:    end of simulation")
+        }
+    }
+}
+
+/// Runs the simulator while comparing its dump to a reference one, line by line
+///
+/// Stops at the first difference or when the reference dump ends, in which case the number of the
+/// last round that was compared is returned. I/O errors are reported as a mismatch.
+pub fn compare<R: BufRead>(expected: R, simulator: &mut Simulator) -> Result<u32, Mismatch> {
+    // Writing to a vector can't fail
+    let mut buffer = Vec::new();
+    write_header(&mut buffer, simulator.seed).unwrap();
+    simulator.dump(&mut buffer).unwrap();
+    let mut ours = take_lines(&mut buffer).into_iter();
+
+    for (i, expected_line) in expected.lines().enumerate() {
+        let expected_line = expected_line.unwrap_or_else(|e| format!("<{}>", e));
+
+        // Run the next round when we run out of lines to compare
+        let our_line = match ours.next() {
+            None if simulator.round < simulator.max_rounds => {
+                simulator.one_round();
+                simulator.dump(&mut buffer).unwrap();
+                ours = take_lines(&mut buffer).into_iter();
+                ours.next()
+            }
+            line => line
+        };
+
+        if our_line.as_ref() != Some(&expected_line) {
+            return Err(Mismatch { line: i + 1, round: simulator.round, expected: expected_line, found: our_line });
+        }
+    }
+
+    Ok(simulator.round)
+}
+
+fn take_lines(buffer: &mut Vec<u8>) -> Vec<String> {
+    let lines = String::from_utf8_lossy(buffer).lines().map(|line| line.to_string()).collect();
+    buffer.clear();
+    lines
+}
@@ -0,0 +1,390 @@
+//! Evolutionary search of ant programs
+//!
+//! A population of programs is improved generation by generation. Each generation, every program
+//! plays against a pool of reference opponents on freshly generated worlds, once as red and once
+//! as black. The best programs survive unchanged (elitism) and the rest of the population is
+//! replaced by offspring of programs selected by fitness, obtained through crossover and mutation.
+//!
+//! All random choices are derived from the seed of the evolution and the number of the
+//! generation, so a run is fully deterministic: it doesn't depend on the amount of threads and it
+//! can be stopped and resumed from a checkpoint without affecting the results.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Cursor, Write};
+use std::num::Wrapping;
+
+use analysis;
+use ant::AntState;
+use generator::WorldParams;
+use instruction::{Condition, InvChance, Instruction, MAX_STATES};
+use optimizer;
+use simulator::Simulator;
+use util::{self, Rng};
+use world::World;
+
+/// The amount of consecutive seeds tried to generate each world before giving up
+const WORLD_ATTEMPTS: u32 = 100;
+
+#[derive(Clone, Debug)]
+pub struct Params {
+    /// The amount of programs in each generation
+    pub population: usize,
+    /// The amount of best programs that survive unchanged to the next generation
+    pub elites: usize,
+    /// The maximum amount of mutations applied to each offspring
+    pub mutations: usize,
+    /// The amount of worlds generated to evaluate each generation
+    pub worlds: usize,
+    /// The amount of rounds of each match
+    pub rounds: u32,
+    /// Offspring with more states than this are discarded
+    pub max_states: usize,
+    pub world_params: WorldParams
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            population: 20,
+            elites: 2,
+            mutations: 3,
+            worlds: 2,
+            rounds: 10_000,
+            max_states: MAX_STATES,
+            world_params: WorldParams::default()
+        }
+    }
+}
+
+impl Params {
+    /// Checks that worlds can be generated from `world_params`, which is not the case when their
+    /// anthills, rocks and food don't fit
+    pub fn validate(&self) -> Result<(), NoWorld> {
+        generate_world(0, &self.world_params).map(|_| ())
+    }
+}
+
+/// None of the seeds tried to generate a world resulted in one
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NoWorld {
+    /// The first seed that was tried
+    pub seed: u32
+}
+
+impl fmt::Display for NoWorld {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unable to generate a world from seeds {} to {}, the anthills, rocks and food don't fit",
+               self.seed, self.seed + WORLD_ATTEMPTS - 1)
+    }
+}
+
+/// A program together with its fitness: the sum of the difference between its score and the
+/// score of its opponent, over all the matches of a generation
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Scored {
+    pub instructions: Vec<Instruction>,
+    pub fitness: i64
+}
+
+#[derive(Clone, Debug)]
+pub struct Evolution {
+    pub params: Params,
+    /// The reference pool against which programs are evaluated
+    pub opponents: Vec<Vec<Instruction>>,
+    pub seed: u32,
+    /// The number of the current generation, starting at 0
+    pub generation: u32,
+    pub population: Vec<Vec<Instruction>>
+}
+
+/// A match between a member of the population and an opponent
+#[derive(Clone, Copy)]
+struct Game {
+    candidate: usize,
+    opponent: usize,
+    world: usize,
+    red: bool
+}
+
+impl Evolution {
+    /// Starts an evolution from the given programs, filling the rest of the first generation with
+    /// mutations of them
+    ///
+    /// Panics if there are no ancestors or if any of them has errors according to
+    /// `analysis::analyze`.
+    pub fn new(params: Params, opponents: Vec<Vec<Instruction>>, ancestors: Vec<Vec<Instruction>>, seed: u32) -> Evolution {
+        assert!(!ancestors.is_empty(), "an evolution needs at least one ancestor");
+
+        let mut rng = rng(seed, 0);
+        let mut population: Vec<_> = ancestors.iter().take(params.population).map(|a| {
+            optimizer::optimize(a).expect("ancestors must not have errors")
+        }).collect();
+        while population.len() < params.population {
+            let parent = &ancestors[population.len() % ancestors.len()];
+            population.push(offspring(parent, None, &params, &mut rng));
+        }
+
+        Evolution { params, opponents, seed, generation: 0, population }
+    }
+
+    /// Plays the matches of the current generation, returning the fitness of each program
+    pub fn evaluate(&self, threads: usize) -> Result<Vec<i64>, NoWorld> {
+        let mut games = Vec::new();
+        for candidate in 0..self.population.len() {
+            for opponent in 0..self.opponents.len() {
+                for world in 0..self.params.worlds {
+                    for &red in &[true, false] {
+                        games.push(Game { candidate, opponent, world, red });
+                    }
+                }
+            }
+        }
+
+        // The threads need their own copy of everything
+        let (population, opponents, worlds, rounds) = (self.population.clone(), self.opponents.clone(), self.worlds()?, self.params.rounds);
+        let scores = util::parallel_map(games.clone(), threads, move |game| {
+            let (candidate, opponent) = (&population[game.candidate], &opponents[game.opponent]);
+            let (ref world, seed) = worlds[game.world];
+            if game.red {
+                let outcome = Simulator::new(world.clone(), candidate.clone(), opponent.clone(), rounds, seed).run();
+                outcome.red_score as i64 - outcome.black_score as i64
+            } else {
+                let outcome = Simulator::new(world.clone(), opponent.clone(), candidate.clone(), rounds, seed).run();
+                outcome.black_score as i64 - outcome.red_score as i64
+            }
+        });
+
+        let mut fitness = vec![0; self.population.len()];
+        for (game, score) in games.iter().zip(scores) {
+            fitness[game.candidate] += score;
+        }
+
+        Ok(fitness)
+    }
+
+    /// Evaluates the current generation and replaces it by the next one
+    ///
+    /// Returns the evaluated programs, sorted from best to worst. The generation is left untouched
+    /// if its worlds can't be generated.
+    pub fn step(&mut self, threads: usize) -> Result<Vec<Scored>, NoWorld> {
+        let fitness = self.evaluate(threads)?;
+        let mut ranked: Vec<_> = self.population.drain(..).zip(fitness).map(|(instructions, fitness)| {
+            Scored { instructions, fitness }
+        }).collect();
+
+        // The sort is stable, so ties keep their order in the population
+        ranked.sort_by(|a, b| b.fitness.cmp(&a.fitness));
+
+        let mut rng = rng(self.seed, 2 * self.generation + 2);
+        let mut next: Vec<_> = ranked.iter().take(self.params.elites).map(|s| s.instructions.clone()).collect();
+        while next.len() < self.params.population {
+            let parent = &ranked[select(ranked.len(), &mut rng)].instructions;
+            let child = if rng.random_int(2) == 0 {
+                let other = &ranked[select(ranked.len(), &mut rng)].instructions;
+                offspring(parent, Some(other), &self.params, &mut rng)
+            } else {
+                offspring(parent, None, &self.params, &mut rng)
+            };
+
+            next.push(child);
+        }
+
+        self.population = next;
+        self.generation += 1;
+        Ok(ranked)
+    }
+
+    /// The worlds of the current generation, together with the seed of the matches played on them
+    fn worlds(&self) -> Result<Vec<(World, u32)>, NoWorld> {
+        let mut rng = rng(self.seed, 2 * self.generation + 1);
+        let mut worlds = Vec::new();
+        while worlds.len() < self.params.worlds {
+            let seed = rng.random_int(16384) as u32;
+            worlds.push((generate_world(seed, &self.params.world_params)?, seed));
+        }
+
+        Ok(worlds)
+    }
+
+    /// Writes the state of the evolution, so it can be resumed later by `read_checkpoint`
+    pub fn write_checkpoint<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "seed {}", self.seed)?;
+        writeln!(out, "generation {}", self.generation)?;
+        for instrs in &self.population {
+            writeln!(out, "brain {}", instrs.len())?;
+            for instr in instrs {
+                writeln!(out, "{}", instr)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resumes an evolution from a checkpoint written by `write_checkpoint`
+    ///
+    /// The parameters and opponents are not part of the checkpoint, so they need to be provided
+    pub fn read_checkpoint<R: BufRead>(reader: R, params: Params, opponents: Vec<Vec<Instruction>>) -> io::Result<Evolution> {
+        let mut lines = reader.lines();
+        let seed = header(lines.next(), "seed")?;
+        let generation = header(lines.next(), "generation")?;
+
+        let mut population = Vec::new();
+        while let Some(line) = lines.next() {
+            let length: usize = header(Some(line), "brain")?;
+            let mut source = String::new();
+            for _ in 0..length {
+                let line = lines.next().unwrap_or_else(|| Err(invalid_checkpoint("truncated brain")))?;
+                source.push_str(&line);
+                source.push('\n');
+            }
+
+            let instrs = Instruction::parse(Cursor::new(source)).map_err(|e| invalid_checkpoint(&e.to_string()))?;
+            if let Some(error) = analysis::analyze(&instrs).into_iter().filter(|d| d.is_error()).next() {
+                return Err(invalid_checkpoint(&format!("brain {}: {}", population.len(), error)));
+            }
+
+            population.push(instrs);
+        }
+
+        // Selection needs someone to choose from
+        if population.is_empty() {
+            return Err(invalid_checkpoint("there are no brains"));
+        }
+
+        if population.len() != params.population {
+            let msg = format!("expected {} brains, found {}", params.population, population.len());
+            return Err(invalid_checkpoint(&msg));
+        }
+
+        Ok(Evolution { params, opponents, seed, generation, population })
+    }
+}
+
+/// A random number generator for each purpose, so evolutions can be resumed at any generation
+///
+/// Stream 0 is used to create the first generation. Each generation then uses two streams: one to
+/// generate its worlds and one to breed the next generation.
+fn rng(seed: u32, stream: u32) -> Rng {
+    Rng::new((Wrapping(seed as usize) * Wrapping(1_000_003) + Wrapping(stream as usize)).0)
+}
+
+/// The first world that can be generated from `seed` or one of the seeds after it
+///
+/// Some seeds fail to produce a world when its elements can't be placed, but trying too many of
+/// them means that the parameters will never fit.
+fn generate_world(seed: u32, params: &WorldParams) -> Result<World, NoWorld> {
+    (seed..seed + WORLD_ATTEMPTS).filter_map(|s| World::generate(s, params)).next().ok_or(NoWorld { seed })
+}
+
+fn invalid_checkpoint(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid checkpoint: {}", msg))
+}
+
+/// Parses a line like `seed 42`
+fn header<T: ::std::str::FromStr>(line: Option<io::Result<String>>, name: &str) -> io::Result<T> {
+    let line = line.unwrap_or_else(|| Err(invalid_checkpoint(&format!("missing {}", name))))?;
+    let words: Vec<_> = line.split_whitespace().collect();
+    match &words[..] {
+        &[word, value] if word == name => value.parse().map_err(|_| invalid_checkpoint(&line)),
+        _ => Err(invalid_checkpoint(&line))
+    }
+}
+
+/// Tournament selection: the best of three random programs, given that they are sorted by fitness
+fn select(count: usize, rng: &mut Rng) -> usize {
+    (0..3).map(|_| rng.random_int(count)).min().unwrap()
+}
+
+/// Creates a child from the given parents, applying crossover if there is a second parent and at
+/// least one mutation
+fn offspring(parent: &[Instruction], other: Option<&[Instruction]>, params: &Params, rng: &mut Rng) -> Vec<Instruction> {
+    let mut child = match other {
+        Some(other) => splice(parent, other, rng),
+        None => parent.to_vec()
+    };
+
+    for _ in 0..1 + rng.random_int(params.mutations.max(1)) {
+        mutate(&mut child, rng);
+    }
+
+    // Mutations tend to leave unreachable states behind
+    match optimizer::optimize(&child) {
+        Ok(ref child) if child.len() <= params.max_states => child.clone(),
+        _ => parent.to_vec()
+    }
+}
+
+/// Changes a random instruction: tweaks the odds of a `Flip`, replaces the condition of a `Sense`
+/// or redirects one of the targets
+pub(crate) fn mutate(instrs: &mut [Instruction], rng: &mut Rng) {
+    let state = rng.random_int(instrs.len());
+    let mutated = match instrs[state].clone() {
+        Instruction::Sense(dir, st1, st2, _) if rng.random_int(2) == 0 => {
+            Instruction::Sense(dir, st1, st2, random_condition(rng))
+        }
+        Instruction::Flip(p, st1, st2) if rng.random_int(2) == 0 => {
+            // The odds must stay within the range of `InvChance`, where 0 would crash the simulator
+            let p = (p as i64 + rng.random_int(5) as i64 - 2).max(1).min(InvChance::max_value() as i64);
+            Instruction::Flip(p as InvChance, st1, st2)
+        }
+        instr => retarget(&instr, rng.random_int(2), rng.random_int(instrs.len()))
+    };
+
+    instrs[state] = mutated;
+}
+
+/// Replaces the target with the given index (modulo the amount of targets) by `new_target`
+fn retarget(instr: &Instruction, index: usize, new_target: usize) -> Instruction {
+    let index = index % instr.next_states().len();
+    let mut i = 0;
+    instr.map_states(|target| {
+        let replaced = if i == index { new_target as AntState } else { target };
+        i += 1;
+        replaced
+    })
+}
+
+fn random_condition(rng: &mut Rng) -> Condition {
+    use instruction::Condition::*;
+    match rng.random_int(10) {
+        0 => Friend,
+        1 => Foe,
+        2 => FriendWithFood,
+        3 => FoeWithFood,
+        4 => Food,
+        5 => Rock,
+        6 => Marker(rng.random_int(6) as u8),
+        7 => FoeMarker,
+        8 => Home,
+        _ => FoeHome
+    }
+}
+
+/// Crossover: copies the part of `other` that can be reached from a random state and redirects
+/// a random target of `instrs` to it
+fn splice(instrs: &[Instruction], other: &[Instruction], rng: &mut Rng) -> Vec<Instruction> {
+    let entry = rng.random_int(other.len());
+    let mut states = HashMap::new();
+    let mut order = vec![entry];
+    states.insert(entry, instrs.len());
+    let mut i = 0;
+    while i < order.len() {
+        for target in other[order[i]].next_states() {
+            let target = target as usize;
+            if !states.contains_key(&target) {
+                states.insert(target, instrs.len() + order.len());
+                order.push(target);
+            }
+        }
+
+        i += 1;
+    }
+
+    let mut result = instrs.to_vec();
+    result.extend(order.iter().map(|&s| other[s].map_states(|target| states[&(target as usize)] as AntState)));
+
+    let state = rng.random_int(instrs.len());
+    result[state] = retarget(&instrs[state], rng.random_int(2), instrs.len());
+    result
+}
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+
+use ant::{AntColor, AntDirection};
+use util::Rng;
+use world::{Cell, World};
+
+/// The parameters of a randomly generated world
+///
+/// The default values follow the rules of the worlds used in the ICFP 2004 tournament
+#[derive(Clone, Debug)]
+pub struct WorldParams {
+    pub width: usize,
+    pub height: usize,
+    /// The length of each side of the hexagonal anthills
+    pub anthill_side: usize,
+    pub rocks: usize,
+    /// Rocks are hexagons with a random side between 1 and this value
+    pub max_rock_side: usize,
+    pub food_blobs: usize,
+    /// Food blobs are squares of this size
+    pub food_blob_size: usize,
+    pub food_per_cell: u16,
+    /// The minimum amount of empty cells between two elements of the world
+    pub gap: usize
+}
+
+impl Default for WorldParams {
+    fn default() -> WorldParams {
+        WorldParams {
+            width: 100,
+            height: 100,
+            anthill_side: 7,
+            rocks: 14,
+            max_rock_side: 2,
+            food_blobs: 11,
+            food_blob_size: 5,
+            food_per_cell: 5,
+            gap: 1
+        }
+    }
+}
+
+/// The maximum amount of random positions tried for each element before giving up
+const MAX_ATTEMPTS: usize = 10_000;
+
+pub fn generate(seed: u32, params: &WorldParams) -> Option<World> {
+    let (width, height) = (params.width, params.height);
+    let mut rng = Rng::new(seed as usize);
+    let mut cells = vec![Cell::new(); width * height];
+
+    // Cells that are part of an element or too close to one
+    let mut blocked = vec![false; width * height];
+
+    // The perimeter is always rocky
+    for (i, cell) in cells.iter_mut().enumerate() {
+        let (x, y) = World::index_to_coords(width, i);
+        if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+            cell.is_rocky = true;
+            blocked[i] = true;
+        }
+    }
+
+    let mut hexagons = vec![
+        (params.anthill_side, Some(AntColor::Red)),
+        (params.anthill_side, Some(AntColor::Black))
+    ];
+
+    // Rocks are represented as hexagons without anthill
+    for _ in 0..params.rocks {
+        let side = 1 + rng.random_int(params.max_rock_side.max(1));
+        hexagons.push((side, None));
+    }
+
+    // Place the hexagonal elements
+    for (side, anthill) in hexagons {
+        let margin = side + params.gap;
+        let shape = match place(&mut rng, width, height, margin, &blocked, |center| hexagon(width, center, side)) {
+            Some(shape) => shape,
+            None => return None
+        };
+
+        for &i in &shape {
+            match anthill {
+                Some(color) => cells[i].anthill = Some(color),
+                None => cells[i].is_rocky = true
+            }
+        }
+
+        block(width, &mut blocked, &shape, params.gap);
+    }
+
+    // Place the food blobs
+    let size = params.food_blob_size;
+    for _ in 0..params.food_blobs {
+        let margin = 1 + params.gap;
+        let square = |corner| {
+            let (x, y) = World::index_to_coords(width - size + 1, corner);
+            let mut shape = Vec::new();
+            for dy in 0..size {
+                for dx in 0..size {
+                    shape.push(World::coords_to_index(width, x + dx, y + dy));
+                }
+            }
+
+            shape
+        };
+
+        // Squares are placed by their top-left corner
+        let shape = match place(&mut rng, width - size + 1, height - size + 1, margin, &blocked, square) {
+            Some(shape) => shape,
+            None => return None
+        };
+
+        for &i in &shape {
+            cells[i].food = params.food_per_cell;
+        }
+
+        block(width, &mut blocked, &shape, params.gap);
+    }
+
+    Some(World::new(width, height, cells))
+}
+
+/// Finds a random position for a shape, such that it doesn't overlap with blocked cells
+///
+/// Positions are indices in a grid of `width * height` cells, from which the shape of the element
+/// is computed. Positions closer than `margin` cells to the edge of that grid are never tried.
+fn place<F>(rng: &mut Rng, width: usize, height: usize, margin: usize, blocked: &[bool], shape: F) -> Option<Vec<usize>>
+where F: Fn(usize) -> Vec<usize> {
+    if width <= 2 * margin || height <= 2 * margin {
+        return None;
+    }
+
+    // Small grids are full of positions that are tried again and again, and shapes are expensive
+    let mut rejected = HashSet::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let x = margin + rng.random_int(width - 2 * margin);
+        let y = margin + rng.random_int(height - 2 * margin);
+        let position = World::coords_to_index(width, x, y);
+        if rejected.contains(&position) {
+            continue;
+        }
+
+        let cells = shape(position);
+        if cells.iter().all(|&i| !blocked[i]) {
+            return Some(cells);
+        }
+
+        rejected.insert(position);
+    }
+
+    None
+}
+
+/// The cells of a hexagon with the given center and length of its sides
+fn hexagon(width: usize, center: usize, side: usize) -> Vec<usize> {
+    let mut cells = vec![center];
+    let mut frontier = vec![center];
+    for _ in 1..side {
+        let mut next_frontier = Vec::new();
+        for &i in &frontier {
+            for dir in AntDirection::all() {
+                let adjacent = World::adjacent_position(width, i, dir);
+                if !cells.contains(&adjacent) {
+                    cells.push(adjacent);
+                    next_frontier.push(adjacent);
+                }
+            }
+        }
+
+        frontier = next_frontier;
+    }
+
+    cells
+}
+
+/// Marks the cells of a shape as blocked, together with all cells up to `gap` steps away from it
+fn block(width: usize, blocked: &mut [bool], shape: &[usize], gap: usize) {
+    let mut frontier = shape.to_vec();
+    for &i in shape {
+        blocked[i] = true;
+    }
+
+    for _ in 0..gap {
+        let mut next_frontier = Vec::new();
+        for &i in &frontier {
+            for dir in AntDirection::all() {
+                let adjacent = World::adjacent_position(width, i, dir);
+                if !blocked[adjacent] {
+                    blocked[adjacent] = true;
+                    next_frontier.push(adjacent);
+                }
+            }
+        }
+
+        frontier = next_frontier;
+    }
+}
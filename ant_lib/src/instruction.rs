@@ -1,12 +1,18 @@
-use std::io::BufRead;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
 
 use ant::{AntColor, AntState};
-use world::Cell;
+use world::World;
 
 pub type MarkerNumber = u8; // 0..5
 pub type InvChance = u16; // 1.. (1 / 1 == 100%, 1 / 2 == 50%, 1 / 3 == 33%)
 
-#[derive(Clone)]
+/// The maximum amount of instructions in a program, so states go from 0 to `MAX_STATES - 1`
+pub const MAX_STATES: usize = 9_999;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Instruction {
     Sense(SenseDir, AntState, AntState, Condition),
     Mark(MarkerNumber, AntState),
@@ -19,31 +25,271 @@ pub enum Instruction {
 }
 
 impl Instruction {
-    pub fn parse<R>(reader: R) -> Vec<Instruction>
+    /// Parses a list of instructions, one per line
+    ///
+    /// Any state operand may be a label instead of a number. A label is defined by prefixing an
+    /// instruction with `name:`, or by putting `name:` on a line of its own, in which case it
+    /// refers to the next instruction. Blank lines and comments (starting with `;`) are ignored.
+    ///
+    /// Parsing does not stop at the first invalid line: all errors found in the input are
+    /// collected and returned together, each of them with its line and column (both 1-indexed)
+    pub fn parse<R>(reader: R) -> Result<Vec<Instruction>, ParseError>
     where R: BufRead {
-        let mut instrs = Vec::new();
+        let mut errors = Vec::new();
+        let mut source = Vec::new();
         for line in reader.lines() {
-            let line = line.unwrap();
-            let line = line.trim();
+            match line {
+                Ok(line) => source.push(line),
+                Err(e) => {
+                    errors.push(SyntaxError::new(source.len() + 1, 1, "", SyntaxErrorKind::Io(e.kind())));
+                    break;
+                }
+            }
+        }
+
+        // Discard everything besides the left side of a ;
+        // (a ; indicates the start of a comment)
+        let lines: Vec<_> = source.iter().map(|line| tokenize(line.split(';').next().unwrap_or(""))).collect();
+
+        // The labels need to be known before parsing, since they may be used before their definition
+        let mut labels = HashMap::new();
+        let mut pending = Vec::new();
+        let mut state = 0;
+        for (i, tokens) in lines.iter().enumerate() {
+            let (defined, rest) = split_labels(tokens);
+            for &t in defined {
+                let name = &t.text[..t.text.len() - 1];
+                if !is_label(name) {
+                    errors.push(t.error(SyntaxErrorKind::InvalidLabel).at_line(i + 1));
+                } else if labels.insert(name, state).is_some() {
+                    errors.push(t.error(SyntaxErrorKind::DuplicateLabel).at_line(i + 1));
+                } else {
+                    pending.push((t, i + 1));
+                }
+            }
+
+            if !rest.is_empty() {
+                state += 1;
+                pending.clear();
+            }
+        }
+
+        // Labels at the end of the file don't refer to any instruction
+        for (t, line_number) in pending {
+            errors.push(t.error(SyntaxErrorKind::DanglingLabel).at_line(line_number));
+        }
+
+        let mut instrs = Vec::new();
+        for (i, tokens) in lines.iter().enumerate() {
+            let line_number = i + 1;
+            let (_, tokens) = split_labels(tokens);
+            if tokens.is_empty() {
+                continue;
+            }
 
-            // Discard everything besides the left side of a ;
-            // (a ; indicates the start of a comment)
-            let line = line.split(';').take(1).next().unwrap_or("");
+            match parse_instruction(tokens, &labels) {
+                Ok(instr) => instrs.push(instr),
+                Err(e) => errors.push(e.at_line(line_number))
+            }
 
-            if line.is_empty() {
-                break;
+            // Only report the first instruction past the limit
+            if instrs.len() == MAX_STATES + 1 {
+                errors.push(SyntaxError::new(line_number, 1, "", SyntaxErrorKind::TooManyStates));
             }
+        }
+
+        if errors.is_empty() {
+            Ok(instrs)
+        } else {
+            errors.sort_by_key(|e| (e.line, e.column));
+            Err(ParseError { errors })
+        }
+    }
 
-            instrs.push(parse_instruction(&line.to_uppercase()));
+    /// Writes a list of instructions in the labelled syntax accepted by `parse`
+    ///
+    /// Every state that is the target of a jump gets a label. Labels are named `L1`, `L2`, etc. in
+    /// order of appearance, except for state 0, which is named `start`.
+    pub fn disassemble<W: Write>(instrs: &[Instruction], mut out: W) -> io::Result<()> {
+        let mut targets = vec![false; instrs.len()];
+        for instr in instrs {
+            for state in instr.next_states() {
+                if let Some(target) = targets.get_mut(state as usize) {
+                    *target = true;
+                }
+            }
         }
 
-        assert!(instrs.len() < 10_000, "Too many states");
+        let mut names = vec![String::new(); instrs.len()];
+        let mut count = 0;
+        for (state, name) in names.iter_mut().enumerate() {
+            if state == 0 {
+                *name = "start".to_string();
+            } else if targets[state] {
+                count += 1;
+                *name = format!("L{}", count);
+            }
+        }
 
-        instrs
+        for (state, instr) in instrs.iter().enumerate() {
+            if targets[state] {
+                writeln!(out, "{}:", names[state])?;
+            }
+
+            // States outside the program have no label, so they are kept as numbers
+            let text = instr.format_with(|s| names.get(s as usize).cloned().unwrap_or_else(|| s.to_string()));
+            writeln!(out, "    {}", text)?;
+        }
+
+        Ok(())
+    }
+
+    /// The same instruction, with each of its states replaced by the result of `f`
+    pub fn map_states<F: FnMut(AntState) -> AntState>(&self, mut f: F) -> Instruction {
+        use self::Instruction::*;
+        match *self {
+            Sense(dir, st1, st2, cond) => Sense(dir, f(st1), f(st2), cond),
+            Mark(i, st) => Mark(i, f(st)),
+            Unmark(i, st) => Unmark(i, f(st)),
+            PickUp(st1, st2) => PickUp(f(st1), f(st2)),
+            Drop(st) => Drop(f(st)),
+            Turn(dir, st) => Turn(dir, f(st)),
+            Move(st1, st2) => Move(f(st1), f(st2)),
+            Flip(p, st1, st2) => Flip(p, f(st1), f(st2))
+        }
+    }
+
+    /// Formats the instruction, using `state` to format its states
+    fn format_with<F: Fn(AntState) -> String>(&self, state: F) -> String {
+        use self::Instruction::*;
+        match *self {
+            Sense(dir, st1, st2, cond) => format!("Sense {} {} {} {}", dir.name(), state(st1), state(st2), cond),
+            Mark(i, st) => format!("Mark {} {}", i, state(st)),
+            Unmark(i, st) => format!("Unmark {} {}", i, state(st)),
+            PickUp(st1, st2) => format!("PickUp {} {}", state(st1), state(st2)),
+            Drop(st) => format!("Drop {}", state(st)),
+            Turn(dir, st) => format!("Turn {} {}", dir.name(), state(st)),
+            Move(st1, st2) => format!("Move {} {}", state(st1), state(st2)),
+            Flip(p, st1, st2) => format!("Flip {} {} {}", p, state(st1), state(st2))
+        }
+    }
+
+    /// The states this instruction may jump to
+    pub fn next_states(&self) -> Vec<AntState> {
+        use self::Instruction::*;
+        match *self {
+            Sense(_, st1, st2, _) | PickUp(st1, st2) | Move(st1, st2) | Flip(_, st1, st2) => vec![st1, st2],
+            Mark(_, st) | Unmark(_, st) | Drop(st) | Turn(_, st) => vec![st]
+        }
     }
 }
 
-#[derive(Clone, Copy)]
+/// Writes the instruction in the same syntax accepted by `parse`
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format_with(|s| s.to_string()))
+    }
+}
+
+/// The errors found while parsing a list of instructions
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub errors: Vec<SyntaxError>
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f, "")?;
+            }
+
+            write!(f, "{}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        "invalid ant instructions"
+    }
+}
+
+/// A single error, located at the offending token
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub kind: SyntaxErrorKind
+}
+
+impl SyntaxError {
+    fn new(line: usize, column: usize, token: &str, kind: SyntaxErrorKind) -> SyntaxError {
+        SyntaxError { line, column, token: token.to_string(), kind }
+    }
+
+    fn at_line(self, line: usize) -> SyntaxError {
+        SyntaxError { line, ..self }
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind.description())?;
+        if !self.token.is_empty() {
+            write!(f, " `{}`", self.token)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SyntaxErrorKind {
+    Io(io::ErrorKind),
+    UnknownInstruction,
+    MissingOperand,
+    UnexpectedOperand,
+    InvalidState,
+    InvalidMarker,
+    InvalidChance,
+    InvalidSenseDir,
+    InvalidTurnDir,
+    InvalidCondition,
+    InvalidLabel,
+    DuplicateLabel,
+    UndefinedLabel,
+    DanglingLabel,
+    TooManyStates
+}
+
+impl SyntaxErrorKind {
+    fn description(self) -> &'static str {
+        use self::SyntaxErrorKind::*;
+        match self {
+            Io(_)              => "unable to read line",
+            UnknownInstruction => "unknown instruction",
+            MissingOperand     => "missing operand after",
+            UnexpectedOperand  => "unexpected operand",
+            InvalidState       => "invalid state (expected a number between 0 and 9998 or a label)",
+            InvalidMarker      => "invalid marker (expected a number between 0 and 5)",
+            InvalidChance      => "invalid flip chance (expected a number between 0 and 65535)",
+            InvalidSenseDir    => "invalid sense direction",
+            InvalidTurnDir     => "invalid turn direction",
+            InvalidCondition   => "invalid condition",
+            InvalidLabel       => "invalid label",
+            DuplicateLabel     => "duplicate label",
+            UndefinedLabel     => "undefined label",
+            DanglingLabel      => "label without an instruction after it",
+            TooManyStates      => "too many states (the maximum is 9999)"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum SenseDir {
     Here,
     Ahead,
@@ -51,13 +297,13 @@ pub enum SenseDir {
     RightAhead
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum TurnDir {
     Left,
     Right
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Condition {
     Friend,
     Foe,
@@ -71,84 +317,217 @@ pub enum Condition {
     FoeHome
 }
 
+impl SenseDir {
+    /// Parses the name of a direction, ignoring case
+    pub fn from_name(name: &str) -> Option<SenseDir> {
+        use self::SenseDir::*;
+        match &name.to_uppercase()[..] {
+            "HERE"       => Some(Here),
+            "AHEAD"      => Some(Ahead),
+            "LEFTAHEAD"  => Some(LeftAhead),
+            "RIGHTAHEAD" => Some(RightAhead),
+            _            => None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        use self::SenseDir::*;
+        match self {
+            Here       => "Here",
+            Ahead      => "Ahead",
+            LeftAhead  => "LeftAhead",
+            RightAhead => "RightAhead"
+        }
+    }
+}
+
+impl TurnDir {
+    /// Parses the name of a direction, ignoring case
+    pub fn from_name(name: &str) -> Option<TurnDir> {
+        match &name.to_uppercase()[..] {
+            "LEFT"  => Some(TurnDir::Left),
+            "RIGHT" => Some(TurnDir::Right),
+            _       => None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TurnDir::Left  => "Left",
+            TurnDir::Right => "Right"
+        }
+    }
+}
+
 impl Condition {
-    pub fn eval(&self, cell: &Cell, color: AntColor) -> bool {
+    /// Parses the name of a condition, ignoring case. Markers are not supported, since they take a
+    /// number as well.
+    pub fn from_name(name: &str) -> Option<Condition> {
+        use self::Condition::*;
+        match &name.to_uppercase()[..] {
+            "FRIEND"         => Some(Friend),
+            "FOE"            => Some(Foe),
+            "FRIENDWITHFOOD" => Some(FriendWithFood),
+            "FOEWITHFOOD"    => Some(FoeWithFood),
+            "FOOD"           => Some(Food),
+            "ROCK"           => Some(Rock),
+            "FOEMARKER"      => Some(FoeMarker),
+            "HOME"           => Some(Home),
+            "FOEHOME"        => Some(FoeHome),
+            _                => None
+        }
+    }
+
+    /// Evaluates the condition on the cell at the given index, as sensed by an ant of `color`
+    pub fn eval(&self, world: &World, position: usize, color: AntColor) -> bool {
         use self::Condition::*;
-        match (*self, &cell.ant) {
-            (Rock          , _             ) => cell.is_rocky,
-            (Food          , _             ) => cell.food > 0,
-            (Home          , _             ) => cell.anthill == Some(color),
-            (FoeHome       , _             ) => cell.anthill == Some(color.enemy()),
-            (Marker(i)     , _             ) => cell.markers(color).is_set(i),
-            (FoeMarker     , _             ) => cell.markers(color.enemy()).any(),
-            (_             , &None         ) => false,
-            (Friend        , &Some(ref ant)) => ant.color == color,
-            (Foe           , &Some(ref ant)) => ant.color != color,
-            (FriendWithFood, &Some(ref ant)) => ant.color == color && ant.has_food,
-            (FoeWithFood   , &Some(ref ant)) => ant.color != color && ant.has_food,
+        let cell = &world.cells[position];
+        match (*self, world.ant_at(position)) {
+            (Rock          , _        ) => cell.is_rocky,
+            (Food          , _        ) => cell.food > 0,
+            (Home          , _        ) => cell.anthill == Some(color),
+            (FoeHome       , _        ) => cell.anthill == Some(color.enemy()),
+            (Marker(i)     , _        ) => cell.markers(color).is_set(i),
+            (FoeMarker     , _        ) => cell.markers(color.enemy()).any(),
+            (_             , None     ) => false,
+            (Friend        , Some(ant)) => ant.color == color,
+            (Foe           , Some(ant)) => ant.color != color,
+            (FriendWithFood, Some(ant)) => ant.color == color && ant.has_food,
+            (FoeWithFood   , Some(ant)) => ant.color != color && ant.has_food,
         }
     }
 }
 
-fn parse_instruction(s: &str) -> Instruction {
-    use self::Instruction::*;
-    let words: Vec<_> = s.split_whitespace().collect();
-    match words[..] {
-        ["SENSE", sensedir, st1, st2, cond] =>
-            Sense(parse_sense_dir(sensedir), parse_state(st1), parse_state(st2), parse_condition(cond)),
-        ["SENSE", sensedir, st1, st2, "MARKER", i] =>
-            Sense(parse_sense_dir(sensedir), parse_state(st1), parse_state(st2), Condition::Marker(parse_marker(i))),
-        ["MARK", i, st] => Mark(parse_marker(i), parse_state(st)),
-        ["UNMARK", i, st] => Unmark(parse_marker(i), parse_state(st)),
-        ["PICKUP", st1, st2] => PickUp(parse_state(st1), parse_state(st2)),
-        ["DROP", st] => Drop(parse_state(st)),
-        ["TURN", lr, st] => Turn(parse_turn_dir(lr), parse_state(st)),
-        ["MOVE", st1, st2] => Move(parse_state(st1), parse_state(st2)),
-        ["FLIP", p, st1, st2] => Flip(p.parse().unwrap(), parse_state(st1), parse_state(st2)),
-        _ => panic!("Unknown instruction: {}", s)
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Condition::*;
+        let name = match *self {
+            Friend         => "Friend",
+            Foe            => "Foe",
+            FriendWithFood => "FriendWithFood",
+            FoeWithFood    => "FoeWithFood",
+            Food           => "Food",
+            Rock           => "Rock",
+            Marker(i)      => return write!(f, "Marker {}", i),
+            FoeMarker      => "FoeMarker",
+            Home           => "Home",
+            FoeHome        => "FoeHome"
+        };
+
+        write!(f, "{}", name)
     }
 }
 
-fn parse_state(s: &str) -> AntState {
-    s.parse().into_iter().filter(|&x| x < 10_000).next().expect("Invalid AntState")
+/// A word of an instruction, together with its (1-indexed) column
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    column: usize,
+    text: &'a str
 }
 
-fn parse_marker(s: &str) -> MarkerNumber {
-    // NOTE: the haskell code uses modulo to handle overflow. We panic instead.
-    s.parse().into_iter().filter(|&x| x < 6).next().expect("Invalid MarkerNumber")
+impl<'a> Token<'a> {
+    fn error(self, kind: SyntaxErrorKind) -> SyntaxError {
+        // The line number is filled in by the caller
+        SyntaxError::new(0, self.column, self.text, kind)
+    }
 }
 
-fn parse_sense_dir(s: &str) -> SenseDir {
-    use self::SenseDir::*;
-    match s {
-        "HERE"       => Here,
-        "AHEAD"      => Ahead,
-        "LEFTAHEAD"  => LeftAhead,
-        "RIGHTAHEAD" => RightAhead,
-        _            => panic!("Invalid SenseDir: {}", s)
+fn tokenize<'a>(line: &'a str) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices().chain(Some((line.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                let column = line[..s].chars().count() + 1;
+                tokens.push(Token { column, text: &line[s..i] });
+                start = None;
+            }
+            _ => ()
+        }
     }
+
+    tokens
+}
+
+/// Splits the label definitions at the start of a line from the instruction that follows them
+fn split_labels<'a, 'b>(tokens: &'b [Token<'a>]) -> (&'b [Token<'a>], &'b [Token<'a>]) {
+    let count = tokens.iter().take_while(|t| t.text.ends_with(':')).count();
+    tokens.split_at(count)
 }
 
-fn parse_turn_dir(s: &str) -> TurnDir {
-    match s {
-        "LEFT"  => TurnDir::Left,
-        "RIGHT" => TurnDir::Right,
-        _       => panic!("Invalid TurnDir: {}", s)
+fn is_label(name: &str) -> bool {
+    name.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn parse_instruction(tokens: &[Token], labels: &HashMap<&str, AntState>) -> Result<Instruction, SyntaxError> {
+    use self::Instruction::*;
+    let opcode = tokens[0];
+    let operands = &tokens[1..];
+
+    // Checks the amount of operands before anything else, so the slice patterns below can't fail
+    let expected_operands = match &opcode.text.to_uppercase()[..] {
+        "SENSE" if operands.get(3).map(|t| t.text.to_uppercase()) == Some("MARKER".to_string()) => 5,
+        "SENSE" => 4,
+        "MARK" | "UNMARK" | "TURN" | "PICKUP" | "MOVE" => 2,
+        "DROP" => 1,
+        "FLIP" => 3,
+        _ => return Err(opcode.error(SyntaxErrorKind::UnknownInstruction))
+    };
+
+    if operands.len() < expected_operands {
+        let last = tokens[operands.len()];
+        return Err(last.error(SyntaxErrorKind::MissingOperand));
+    } else if operands.len() > expected_operands {
+        return Err(operands[expected_operands].error(SyntaxErrorKind::UnexpectedOperand));
     }
+
+    let instr = match (&opcode.text.to_uppercase()[..], operands) {
+        ("SENSE", &[sensedir, st1, st2, _, i]) =>
+            Sense(parse_sense_dir(sensedir)?, parse_state(st1, labels)?, parse_state(st2, labels)?, Condition::Marker(parse_marker(i)?)),
+        ("SENSE", &[sensedir, st1, st2, cond]) =>
+            Sense(parse_sense_dir(sensedir)?, parse_state(st1, labels)?, parse_state(st2, labels)?, parse_condition(cond)?),
+        ("MARK", &[i, st]) => Mark(parse_marker(i)?, parse_state(st, labels)?),
+        ("UNMARK", &[i, st]) => Unmark(parse_marker(i)?, parse_state(st, labels)?),
+        ("PICKUP", &[st1, st2]) => PickUp(parse_state(st1, labels)?, parse_state(st2, labels)?),
+        ("DROP", &[st]) => Drop(parse_state(st, labels)?),
+        ("TURN", &[lr, st]) => Turn(parse_turn_dir(lr)?, parse_state(st, labels)?),
+        ("MOVE", &[st1, st2]) => Move(parse_state(st1, labels)?, parse_state(st2, labels)?),
+        ("FLIP", &[p, st1, st2]) => Flip(parse_chance(p)?, parse_state(st1, labels)?, parse_state(st2, labels)?),
+        _ => unreachable!()
+    };
+
+    Ok(instr)
 }
 
-fn parse_condition(s: &str) -> Condition {
-    use self::Condition::*;
-    match s {
-        "FRIEND"         => Friend,
-        "FOE"            => Foe,
-        "FRIENDWITHFOOD" => FriendWithFood,
-        "FOEWITHFOOD"    => FoeWithFood,
-        "FOOD"           => Food,
-        "ROCK"           => Rock,
-        "FOEMARKER"      => FoeMarker,
-        "HOME"           => Home,
-        "FOEHOME"        => FoeHome,
-        _                => panic!("Invalid condition: {}", s)
+fn parse_state(t: Token, labels: &HashMap<&str, AntState>) -> Result<AntState, SyntaxError> {
+    if is_label(t.text) {
+        labels.get(t.text).cloned().ok_or_else(|| t.error(SyntaxErrorKind::UndefinedLabel))
+    } else {
+        t.text.parse().into_iter().filter(|&x| (x as usize) < MAX_STATES).next().ok_or_else(|| t.error(SyntaxErrorKind::InvalidState))
     }
 }
+
+fn parse_marker(t: Token) -> Result<MarkerNumber, SyntaxError> {
+    // NOTE: the haskell code uses modulo to handle overflow. We report an error instead.
+    t.text.parse().into_iter().filter(|&x| x < 6).next().ok_or_else(|| t.error(SyntaxErrorKind::InvalidMarker))
+}
+
+fn parse_chance(t: Token) -> Result<InvChance, SyntaxError> {
+    // `Flip 0` is syntactically valid, but `analysis` reports it as an error because the simulator
+    // can't run it
+    t.text.parse().map_err(|_| t.error(SyntaxErrorKind::InvalidChance))
+}
+
+fn parse_sense_dir(t: Token) -> Result<SenseDir, SyntaxError> {
+    SenseDir::from_name(t.text).ok_or_else(|| t.error(SyntaxErrorKind::InvalidSenseDir))
+}
+
+fn parse_turn_dir(t: Token) -> Result<TurnDir, SyntaxError> {
+    TurnDir::from_name(t.text).ok_or_else(|| t.error(SyntaxErrorKind::InvalidTurnDir))
+}
+
+fn parse_condition(t: Token) -> Result<Condition, SyntaxError> {
+    Condition::from_name(t.text).ok_or_else(|| t.error(SyntaxErrorKind::InvalidCondition))
+}
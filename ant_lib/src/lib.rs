@@ -1,16 +1,27 @@
 #![feature(conservative_impl_trait, inclusive_range_syntax, slice_patterns, pub_restricted)]
 
+pub mod analysis;
 mod ant;
+mod brain;
+pub mod compiler;
+pub mod dump;
+pub mod evolve;
+mod generator;
 mod instruction;
+pub mod optimizer;
 mod simulator;
+mod snapshot;
 pub mod test_data;
+pub mod tournament;
 mod util;
 mod world;
 
 #[cfg(test)]
 mod test;
 
-pub use ant::{AntColor, AntDirection};
-pub use instruction::{Instruction, TurnDir};
-pub use simulator::{Outcome, Simulator};
-pub use world::{Cell, World};
+pub use ant::{Ant, AntColor, AntDirection};
+pub use generator::WorldParams;
+pub use instruction::{Instruction, ParseError, SyntaxError, SyntaxErrorKind, TurnDir};
+pub use simulator::{GameState, Outcome, Simulator};
+pub use snapshot::Snapshot;
+pub use world::{Cell, Violation, World, WorldError};
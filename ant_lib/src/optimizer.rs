@@ -0,0 +1,89 @@
+//! Optimizations over ant programs
+//!
+//! Ants can only have 9,999 states, so brains (especially generated ones) benefit from removing
+//! the states they don't need. The optimizations here never change the behavior of the ants: an
+//! optimized program results in exactly the same game as the original one, for any seed.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use analysis::{self, Diagnostic};
+use ant::AntState;
+use instruction::Instruction;
+
+/// Returns an equivalent program without unreachable states, where equivalent states are merged
+///
+/// State 0 of the result corresponds to state 0 of the original program and the rest of the
+/// states keep their relative order.
+///
+/// Programs that would crash the simulator can't be optimized, so the errors reported by
+/// `analysis::analyze` are returned instead.
+pub fn optimize(instrs: &[Instruction]) -> Result<Vec<Instruction>, Vec<Diagnostic>> {
+    let errors: Vec<_> = analysis::analyze(instrs).into_iter().filter(|d| d.is_error()).collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let instrs: Vec<_> = instrs.iter().map(fold_flip).collect();
+    let reachable = analysis::reachable_states(&instrs);
+    let states: Vec<_> = (0..instrs.len()).filter(|&s| reachable[s]).collect();
+    let (block, count) = partition(&instrs, &states);
+
+    // Each block becomes a state. Since blocks are numbered in order of their first state, the
+    // block of state 0 is block 0.
+    let mut program = vec![None; count];
+    for &s in &states {
+        if program[block[s]].is_none() {
+            program[block[s]] = Some(instrs[s].map_states(|target| block[target as usize] as AntState));
+        }
+    }
+
+    Ok(program.into_iter().map(|instr| instr.unwrap()).collect())
+}
+
+/// A `Flip 1` always goes to its first state. It can't be removed, because it still takes a round
+/// and consumes a random number, but it no longer needs to refer to the second state.
+fn fold_flip(instr: &Instruction) -> Instruction {
+    match *instr {
+        Instruction::Flip(1, st, _) => Instruction::Flip(1, st, st),
+        ref instr => instr.clone()
+    }
+}
+
+/// Splits the given states into blocks of equivalent states, in the style of DFA minimization
+///
+/// Two states are equivalent when they have the same instruction and their targets are
+/// equivalent as well. Returns the block of each state, indexed by state, and the amount of blocks.
+fn partition(instrs: &[Instruction], states: &[usize]) -> (Vec<usize>, usize) {
+    let mut block = vec![0; instrs.len()];
+
+    // Start by telling states apart only by their instruction, ignoring the targets
+    let keys: Vec<_> = states.iter().map(|&s| instrs[s].map_states(|_| 0)).collect();
+    let mut count = number_blocks(states, &keys, &mut block);
+
+    // Then split blocks whose states have targets in different blocks, until nothing changes
+    loop {
+        let keys: Vec<_> = states.iter().map(|&s| {
+            let targets: Vec<_> = instrs[s].next_states().into_iter().map(|t| block[t as usize]).collect();
+            (block[s], targets)
+        }).collect();
+
+        let new_count = number_blocks(states, &keys, &mut block);
+        if new_count == count {
+            return (block, count);
+        }
+
+        count = new_count;
+    }
+}
+
+/// Gives states with the same key the same block, numbering blocks in order of appearance
+fn number_blocks<K: Eq + Hash>(states: &[usize], keys: &[K], block: &mut [usize]) -> usize {
+    let mut numbers = HashMap::new();
+    for (&s, key) in states.iter().zip(keys) {
+        let next = numbers.len();
+        block[s] = *numbers.entry(key).or_insert(next);
+    }
+
+    numbers.len()
+}
@@ -1,7 +1,10 @@
-use std::usize;
+use std::io::{self, Write};
 
-use ant::{Ant, AntColor, AntDirection, AntState};
-use instruction::{Instruction, SenseDir, TurnDir};
+use ant::{AntColor, AntDirection, AntId};
+use brain::{self, Brain, Op};
+use dump;
+use instruction::Instruction;
+use snapshot::Snapshot;
 use util::Rng;
 use world::World;
 
@@ -9,8 +12,12 @@ pub struct Simulator {
     pub world: World,
     red_instructions: Vec<Instruction>,
     black_instructions: Vec<Instruction>,
-    pub ants: Vec<usize>,
+    red_brain: Brain,
+    black_brain: Brain,
+    neighbours: Neighbours,
     rng: Rng,
+    /// The seed of the random number generator at the start of the game
+    pub seed: u32,
     pub round: u32,
     pub max_rounds: u32,
 }
@@ -21,14 +28,18 @@ impl Simulator {
                black_instructions: Vec<Instruction>,
                max_rounds: u32,
                seed: u32) -> Simulator {
-        let ants = world.populate();
+        world.populate();
 
+        let neighbours = Neighbours::new(world.width);
         Simulator {
             world,
+            red_brain: Brain::new(&red_instructions),
+            black_brain: Brain::new(&black_instructions),
             red_instructions,
             black_instructions,
-            ants,
+            neighbours,
             rng: Rng::new(seed as usize),
+            seed,
             round: 0,
             max_rounds
         }
@@ -42,23 +53,86 @@ impl Simulator {
                        seed)
     }
 
+    /// The full state of the game, from which it can be resumed with `restore`
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            world: self.world.clone(),
+            red_instructions: self.red_instructions.clone(),
+            black_instructions: self.black_instructions.clone(),
+            rng_state: self.rng.state(),
+            seed: self.seed,
+            round: self.round,
+            max_rounds: self.max_rounds
+        }
+    }
+
+    /// Resumes a game from a snapshot, which continues exactly like the original game would
+    pub fn restore(snapshot: Snapshot) -> Simulator {
+        let neighbours = Neighbours::new(snapshot.world.width);
+        Simulator {
+            red_brain: Brain::new(&snapshot.red_instructions),
+            black_brain: Brain::new(&snapshot.black_instructions),
+            world: snapshot.world,
+            red_instructions: snapshot.red_instructions,
+            black_instructions: snapshot.black_instructions,
+            neighbours,
+            rng: Rng::from_state(snapshot.rng_state),
+            seed: snapshot.seed,
+            round: snapshot.round,
+            max_rounds: snapshot.max_rounds
+        }
+    }
+
+    /// The part of the game that changes from round to round, which is enough to bring it back to
+    /// this round with `set_state`
+    pub fn state(&self) -> GameState {
+        GameState { world: self.world.clone(), rng_state: self.rng.state(), round: self.round }
+    }
+
+    /// Brings the game to a state returned by `state`, which must come from this same game
+    ///
+    /// The brains are kept as they are, so this is much cheaper than `restore`.
+    pub fn set_state(&mut self, state: &GameState) {
+        self.world.clone_from(&state.world);
+        self.rng = Rng::from_state(state.rng_state);
+        self.round = state.round;
+    }
+
+    /// The program run by the ants of the given color
+    pub fn instructions(&self, color: AntColor) -> &[Instruction] {
+        match color {
+            AntColor::Red => &self.red_instructions,
+            AntColor::Black => &self.black_instructions
+        }
+    }
+
     pub fn one_round(&mut self) {
+        self.run_round(|_| ());
+    }
+
+    /// Runs a round like `one_round` and returns whether the ant with the given id ran an
+    /// instruction, which it doesn't if it is resting, dead or killed before its turn
+    pub fn one_round_for(&mut self, id: usize) -> bool {
+        let mut acted = false;
+        self.run_round(|ant| acted |= ant == id);
+        acted
+    }
+
+    /// Runs a round, calling `on_turn` with the id of each ant right before it runs an instruction
+    fn run_round<F: FnMut(usize)>(&mut self, mut on_turn: F) {
         if self.round < self.max_rounds {
             self.round += 1;
         }
 
-        // For each ant, run its current instruction
-        let mut position_updates = Vec::new();
-        for i in 0..self.ants.len() {
-            let ant_position = self.ants[i];
-
-            // Ignore dead ants
-            if ant_position == usize::MAX {
-                continue;
-            }
-
+        // For each ant, in order of id, run its current instruction
+        for id in 0..self.world.ants.len() {
             let (state, color) = {
-                let ant = self.ant_mut(ant_position);
+                let ant = &mut self.world.ants[id];
+
+                // Ignore dead ants
+                if !ant.alive {
+                    continue;
+                }
 
                 if ant.resting > 0 {
                     ant.resting -= 1;
@@ -69,18 +143,13 @@ impl Simulator {
             };
 
             // Get and run the corresponding instruction
-            let instruction = self.get_instruction(state, color);
-            self.run_instruction(ant_position, instruction, &mut position_updates);
-
-            // An instruction generates position updates, which are processed afterwards
-            // Note: ant deaths are also propagated as position updates
-            for &(old_position, new_position) in &position_updates {
-                assert!(old_position != usize::MAX);
-                *self.ants.iter_mut().find(|&&mut pos| pos == old_position).unwrap() = new_position;
-            }
+            let op = match color {
+                AntColor::Red => self.red_brain.op(state),
+                AntColor::Black => self.black_brain.op(state)
+            };
 
-            // Clear the position updates
-            position_updates.clear();
+            on_turn(id);
+            self.run_op(id, op);
         }
     }
 
@@ -113,63 +182,45 @@ impl Simulator {
         Outcome { red_score, red_alive, black_score, black_alive, food_left, round }
     }
 
-    fn get_instruction(&self, state: AntState, color: AntColor) -> Instruction {
-        match color {
-            AntColor::Red => self.red_instructions[state as usize].clone(),
-            AntColor::Black => self.black_instructions[state as usize].clone()
-        }
-    }
-
-    fn ant(&self, pos: usize) -> &Ant {
-        self.world.cells[pos].ant.as_ref().unwrap()
+    /// Writes the state of every cell in the format of the ICFP 2004 reference dumps
+    pub fn dump<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        dump::write_round(out, &self.world, self.round)
     }
 
-    fn ant_mut(&mut self, pos: usize) -> &mut Ant {
-        self.world.cells[pos].ant.as_mut().unwrap()
-    }
+    fn run_op(&mut self, id: usize, op: Op) {
+        let (ant_pos, ant_dir, ant_color) = {
+            let ant = &self.world.ants[id];
+            (ant.position, ant.direction, ant.color)
+        };
 
-    fn run_instruction(&mut self, ant_pos: usize, instruction: Instruction, position_updates: &mut Vec<(usize, usize)>) {
         // Run the instruction
-        use self::Instruction::*;
-        match instruction {
-            Sense(sense_dir, st1, st2, cond) => {
-                let (ant_dir, ant_color) = {
-                    let ant = self.ant(ant_pos);
-                    (ant.direction, ant.color)
+        match op {
+            Op::Sense(rotation, cond, st1, st2) => {
+                let sensed_position = if rotation == brain::HERE {
+                    ant_pos
+                } else {
+                    self.neighbours.get(ant_pos, ant_dir as u8 + rotation)
                 };
 
-                let new_state = {
-                    let sensed_position = sensed_position(self.world.width, ant_pos, ant_dir, sense_dir);
-                    let sensed_cell = &self.world.cells[sensed_position];
-                    if cond.eval(sensed_cell, ant_color) {
-                        st1
-                    } else {
-                        st2
-                    }
+                let new_state = if cond.eval(&self.world, sensed_position, ant_color) {
+                    st1
+                } else {
+                    st2
                 };
 
-                self.ant_mut(ant_pos).state = new_state
+                self.world.ants[id].state = new_state
             }
-            Mark(mark, new_state) => {
-                let cell = &mut self.world.cells[ant_pos];
-                let ant_color = cell.ant.as_ref().unwrap().color;
-                cell.markers_mut(ant_color).set_bit(mark);
-                cell.ant.as_mut().unwrap().state = new_state
+            Op::Mark(mark, new_state) => {
+                self.world.cells[ant_pos].markers_mut(ant_color).set_bit(mark);
+                self.world.ants[id].state = new_state
             }
-            Unmark(mark, new_state) => {
-                let cell = &mut self.world.cells[ant_pos];
-                let ant = cell.ant.as_mut().unwrap();
-                if let AntColor::Red = ant.color {
-                    cell.markers_red.clear(mark);
-                } else {
-                    cell.markers_black.clear(mark);
-                }
-
-                ant.state = new_state
+            Op::Unmark(mark, new_state) => {
+                self.world.cells[ant_pos].markers_mut(ant_color).clear(mark);
+                self.world.ants[id].state = new_state
             }
-            PickUp(success_state, failure_state) => {
+            Op::PickUp(success_state, failure_state) => {
                 let cell = &mut self.world.cells[ant_pos];
-                let ant = cell.ant.as_mut().unwrap();
+                let ant = &mut self.world.ants[id];
                 if ant.has_food || cell.food == 0 {
                     ant.state = failure_state;
                 } else {
@@ -178,25 +229,23 @@ impl Simulator {
                     ant.state = success_state;
                 }
             }
-            Drop(new_state) => {
+            Op::Drop(new_state) => {
                 let cell = &mut self.world.cells[ant_pos];
-                let ant = cell.ant.as_mut().unwrap();
+                let ant = &mut self.world.ants[id];
                 if ant.has_food {
                     cell.food += 1;
                     ant.has_food = false;
-                    ant.state = new_state;
                 }
 
                 ant.state = new_state;
             }
-            Turn(turn_direction, new_state) => {
-                let ant = self.ant_mut(ant_pos);
-                ant.direction = ant.direction.turn(turn_direction);
+            Op::Turn(rotation, new_state) => {
+                let ant = &mut self.world.ants[id];
+                ant.direction = AntDirection::from_u8(ant.direction as u8 + rotation);
                 ant.state = new_state;
             }
-            Move(success_state, failure_state) => {
-                let ant_dir = self.ant(ant_pos).direction;
-                let target_pos = World::adjacent_position(self.world.width, ant_pos, ant_dir);
+            Op::Move(success_state, failure_state) => {
+                let target_pos = self.neighbours.get(ant_pos, ant_dir as u8);
 
                 let target_occupied = {
                     let target_cell = &self.world.cells[target_pos];
@@ -205,77 +254,98 @@ impl Simulator {
 
                 // Stop here if the target cell is occupied
                 if target_occupied {
-                    self.ant_mut(ant_pos).state = failure_state;
+                    self.world.ants[id].state = failure_state;
                     return;
                 }
 
                 // Take the ant from the current place and put it in the target cell
-                let ant = self.world.cells[ant_pos].ant.take();
-                self.world.cells[target_pos].ant = ant;
-
-                // Generate a position update
-                position_updates.push((ant_pos, target_pos));
+                self.world.cells[ant_pos].ant = None;
+                self.world.cells[target_pos].ant = Some(id as AntId);
 
                 {
                     // Don't forget to rest and update the state
-                    let ant = self.ant_mut(target_pos);
+                    let ant = &mut self.world.ants[id];
+                    ant.position = target_pos;
                     ant.resting = 14;
                     ant.state = success_state;
                 }
 
-                self.kill_surrounded_ants(target_pos, position_updates);
+                self.kill_surrounded_ants(target_pos);
             }
-            Flip(n, st1, st2) => {
+            Op::Flip(n, st1, st2) => {
                 let random = self.rng.random_int(n as usize);
                 let new_state = if random == 0 { st1 } else { st2 };
-                self.ant_mut(ant_pos).state = new_state;
+                self.world.ants[id].state = new_state;
             }
         }
     }
 
-    fn kill_surrounded_ants(&mut self, position: usize, position_updates: &mut Vec<(usize, usize)>) {
+    fn kill_surrounded_ants(&mut self, position: usize) {
         // Check if this ant is surrounded
-        self.kill_surrounded_ant(position, position_updates);
+        self.kill_surrounded_ant(position);
 
         // For each adjacent cell, check if there is a surrounded ant
-        for direction in AntDirection::all() {
-            let adjacent_position = World::adjacent_position(self.world.width, position, direction);
-            self.kill_surrounded_ant(adjacent_position, position_updates);
+        for direction in 0..6 {
+            let adjacent_position = self.neighbours.get(position, direction);
+            self.kill_surrounded_ant(adjacent_position);
         }
     }
 
-    fn kill_surrounded_ant(&mut self, position: usize, position_updates: &mut Vec<(usize, usize)>) {
-        let mut this_ant_dead = false;
-        if let Some(ref this_ant) = self.world.cells[position].ant {
-            this_ant_dead = self.world.adjacent_enemies(position, this_ant.color) >= 5;
-        }
+    fn kill_surrounded_ant(&mut self, position: usize) {
+        let this_ant_dead = match self.world.ant_at(position) {
+            Some(this_ant) => self.world.adjacent_enemies(position, this_ant.color) >= 5,
+            None => false
+        };
 
         if this_ant_dead {
             // Remove from cell and drop food
             let cell = &mut self.world.cells[position];
-            let ant = cell.ant.take().unwrap();
+            let ant = &mut self.world.ants[cell.ant.take().unwrap() as usize];
+            ant.alive = false;
             cell.food += 3;
             if ant.has_food {
                 cell.food += 1;
             }
-
-            // Set the position to an invalid value
-            position_updates.push((position, usize::MAX));
         }
     }
 }
 
-fn sensed_position(width: usize, ant_index: usize, ant_direction: AntDirection, sense_direction: SenseDir) -> usize {
-    use self::SenseDir::*;
-    match sense_direction {
-        Here       => ant_index,
-        Ahead      => World::adjacent_position(width, ant_index, ant_direction),
-        LeftAhead  => World::adjacent_position(width, ant_index, ant_direction.turn(TurnDir::Left)),
-        RightAhead => World::adjacent_position(width, ant_index, ant_direction.turn(TurnDir::Right))
+/// The world, the random number generator and the round of a game, as returned by
+/// `Simulator::state`
+#[derive(Clone, Debug)]
+pub struct GameState {
+    world: World,
+    rng_state: u32,
+    pub round: u32
+}
+
+/// The offset from a cell to each of its neighbours, which depends on the parity of its row
+struct Neighbours {
+    width: usize,
+    offsets: [[isize; 6]; 2]
+}
+
+impl Neighbours {
+    fn new(width: usize) -> Neighbours {
+        let mut offsets = [[0; 6]; 2];
+        for parity in 0..2 {
+            let base = World::coords_to_index(width, 1, 2 + parity);
+            for dir in AntDirection::all() {
+                offsets[parity][dir as usize] = World::adjacent_position(width, base, dir) as isize - base as isize;
+            }
+        }
+
+        Neighbours { width, offsets }
+    }
+
+    /// The position of the neighbour of the given cell in the given direction, modulo 6
+    fn get(&self, position: usize, direction: u8) -> usize {
+        let offset = self.offsets[(position / self.width) % 2][direction as usize % 6];
+        (position as isize + offset) as usize
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct Outcome {
     pub red_score: u16,
     pub red_alive: u16,
@@ -284,3 +354,16 @@ pub struct Outcome {
     pub food_left: u16,
     pub round: u32
 }
+
+impl Outcome {
+    /// The team with the most food in its anthill, if any
+    pub fn winner(&self) -> Option<AntColor> {
+        if self.red_score > self.black_score {
+            Some(AntColor::Red)
+        } else if self.black_score > self.red_score {
+            Some(AntColor::Black)
+        } else {
+            None
+        }
+    }
+}
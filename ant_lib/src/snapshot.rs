@@ -0,0 +1,383 @@
+//! Saved game states, which can be stored in a file and resumed later
+//!
+//! The file format is line based and starts with a header, followed by the cells of the world,
+//! the ants and the brains of both teams:
+//!
+//! ```text
+//! snapshot
+//! round 1234
+//! max_rounds 100000
+//! seed 42
+//! rng 1807434301
+//! world 10 10
+//! # # # # # # # # # #
+//!  # . .2r5 + ...
+//! ants 254
+//! 0 red 12 0 3 1 34 1
+//! ...
+//! red 203
+//! Sense Ahead 1 3 Food
+//! ...
+//! black 203
+//! ...
+//! ```
+//!
+//! Cells are written like in the world format, with optional food and markers after their first
+//! character: `.2r5b1` is a clear cell with 2 units of food, red markers `0b101` and black marker
+//! `0b1`. Each ant is written as `id color state resting direction has_food position alive`.
+
+use std::io::{self, BufRead, Cursor, Write};
+use std::str::FromStr;
+
+use analysis;
+use ant::{Ant, AntColor, AntDirection, AntId, AntState};
+use instruction::Instruction;
+use util::BitField8;
+use world::{Cell, World};
+
+/// The full state of a game, as returned by `Simulator::snapshot`
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// The world, including its ants
+    pub world: World,
+    pub red_instructions: Vec<Instruction>,
+    pub black_instructions: Vec<Instruction>,
+    /// The internal state of the random number generator
+    pub rng_state: u32,
+    /// The seed that the game was started with
+    pub seed: u32,
+    pub round: u32,
+    pub max_rounds: u32
+}
+
+impl Snapshot {
+    /// Writes the snapshot in the format described in the module documentation
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let world = &self.world;
+        writeln!(out, "snapshot")?;
+        writeln!(out, "round {}", self.round)?;
+        writeln!(out, "max_rounds {}", self.max_rounds)?;
+        writeln!(out, "seed {}", self.seed)?;
+        writeln!(out, "rng {}", self.rng_state)?;
+
+        writeln!(out, "world {} {}", world.width, world.height)?;
+        for (y, row) in world.cells.chunks(world.width).enumerate() {
+            // Odd rows are shifted half a cell to the right
+            if y % 2 == 1 {
+                write!(out, " ")?;
+            }
+
+            for (x, cell) in row.iter().enumerate() {
+                if x > 0 {
+                    write!(out, " ")?;
+                }
+
+                write_cell(&mut out, cell)?;
+            }
+
+            writeln!(out, "")?;
+        }
+
+        writeln!(out, "ants {}", world.ants.len())?;
+        for ant in &world.ants {
+            writeln!(out, "{} {} {} {} {} {} {} {}",
+                     ant.id,
+                     ant.color.name(),
+                     ant.state,
+                     ant.resting,
+                     ant.direction as u8,
+                     ant.has_food as u8,
+                     ant.position,
+                     ant.alive as u8)?;
+        }
+
+        for &(color, instrs) in &[(AntColor::Red, &self.red_instructions), (AntColor::Black, &self.black_instructions)] {
+            writeln!(out, "{} {}", color.name(), instrs.len())?;
+            for instr in instrs.iter() {
+                writeln!(out, "{}", instr)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a snapshot written by `write_to`
+    ///
+    /// Fails with `io::ErrorKind::InvalidData` if the input is malformed or describes a game that
+    /// the simulator can't run, e.g. because an ant is outside the world or a brain has errors.
+    pub fn parse<R: BufRead>(reader: R) -> io::Result<Snapshot> {
+        let mut lines = Lines { lines: reader.lines(), number: 0 };
+
+        let words = lines.words()?;
+        if words != ["snapshot"] {
+            return Err(lines.error("expected `snapshot`"));
+        }
+
+        let round = lines.header("round")?;
+        let max_rounds = lines.header("max_rounds")?;
+        let seed = lines.header("seed")?;
+        let rng_state = lines.header("rng")?;
+
+        let (width, height): (usize, usize) = {
+            let words = lines.words()?;
+            match &words[..] {
+                &[ref word, ref width, ref height] if word == "world" => {
+                    match (width.parse(), height.parse()) {
+                        (Ok(width), Ok(height)) => (width, height),
+                        _ => return Err(lines.error("invalid dimensions"))
+                    }
+                }
+                _ => return Err(lines.error("expected `world WIDTH HEIGHT`"))
+            }
+        };
+
+        let mut cells = Vec::with_capacity(width * height);
+        for _ in 0..height {
+            let words = lines.words()?;
+            if words.len() != width {
+                return Err(lines.error(&format!("expected {} cells", width)));
+            }
+
+            for word in &words {
+                let cell = parse_cell(word).map_err(|_| lines.error(&format!("invalid cell `{}`", word)))?;
+                cells.push(cell);
+            }
+        }
+
+        let count = lines.header("ants")?;
+        let mut ants = Vec::with_capacity(count);
+        for id in 0..count {
+            let ant = parse_ant(&lines.words()?).map_err(|_| lines.error("invalid ant"))?;
+            if ant.id as usize != id {
+                return Err(lines.error("ants must be sorted by id"));
+            }
+
+            ants.push(ant);
+        }
+
+        let red_instructions = lines.brain("red")?;
+        let black_instructions = lines.brain("black")?;
+
+        let mut world = World::new(width, height, cells);
+        validate(&world, &ants, &red_instructions, &black_instructions).map_err(|msg| invalid_snapshot(&msg))?;
+        world.place_ants(ants);
+
+        Ok(Snapshot { world, red_instructions, black_instructions, rng_state, seed, round, max_rounds })
+    }
+}
+
+fn invalid_snapshot(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid snapshot: {}", msg))
+}
+
+/// The lines of a snapshot, keeping track of the current line number for error messages
+struct Lines<R> {
+    lines: io::Lines<R>,
+    number: usize
+}
+
+impl<R: BufRead> Lines<R> {
+    fn error(&self, msg: &str) -> io::Error {
+        invalid_snapshot(&format!("line {}: {}", self.number, msg))
+    }
+
+    fn next(&mut self) -> io::Result<String> {
+        self.number += 1;
+        match self.lines.next() {
+            Some(line) => line,
+            None => Err(self.error("unexpected end of file"))
+        }
+    }
+
+    fn words(&mut self) -> io::Result<Vec<String>> {
+        Ok(self.next()?.split_whitespace().map(|w| w.to_string()).collect())
+    }
+
+    /// Parses a line like `round 42`
+    fn header<T: FromStr>(&mut self, name: &str) -> io::Result<T> {
+        let words = self.words()?;
+        match &words[..] {
+            &[ref word, ref value] if word == name => value.parse().map_err(|_| self.error(&format!("invalid {}", name))),
+            _ => Err(self.error(&format!("expected `{} VALUE`", name)))
+        }
+    }
+
+    /// Parses a brain, preceded by a line with the given name and its amount of instructions
+    fn brain(&mut self, name: &str) -> io::Result<Vec<Instruction>> {
+        let length: usize = self.header(name)?;
+        let first = self.number + 1;
+        let mut source = String::new();
+        for _ in 0..length {
+            source.push_str(&self.next()?);
+            source.push('\n');
+        }
+
+        Instruction::parse(Cursor::new(source)).map_err(|e| {
+            invalid_snapshot(&format!("{} brain starting at line {}:\n{}", name, first, e))
+        })
+    }
+}
+
+fn write_cell<W: Write>(out: &mut W, cell: &Cell) -> io::Result<()> {
+    let kind = match (cell.is_rocky, cell.anthill) {
+        (true, _) => '#',
+        (false, Some(AntColor::Red)) => '+',
+        (false, Some(AntColor::Black)) => '-',
+        (false, None) => '.'
+    };
+
+    write!(out, "{}", kind)?;
+    if cell.food > 0 {
+        write!(out, "{}", cell.food)?;
+    }
+
+    if cell.markers_red.any() {
+        write!(out, "r{}", cell.markers_red.bits())?;
+    }
+
+    if cell.markers_black.any() {
+        write!(out, "b{}", cell.markers_black.bits())?;
+    }
+
+    Ok(())
+}
+
+fn parse_cell(word: &str) -> Result<Cell, ()> {
+    let mut cell = Cell::new();
+    match word.chars().next() {
+        Some('#') => cell.is_rocky = true,
+        Some('+') => cell.anthill = Some(AntColor::Red),
+        Some('-') => cell.anthill = Some(AntColor::Black),
+        Some('.') => (),
+        _ => return Err(())
+    }
+
+    // The rest of the word is the food, followed by the markers
+    let (food, rest) = split_number(&word[1..]);
+    if let Some(food) = food {
+        cell.food = number(food)?;
+    }
+
+    let (red, rest) = match rest.chars().next() {
+        Some('r') => split_number(&rest[1..]),
+        _ => (None, rest)
+    };
+
+    let (black, rest) = match rest.chars().next() {
+        Some('b') => split_number(&rest[1..]),
+        _ => (None, rest)
+    };
+
+    if !rest.is_empty() {
+        return Err(());
+    }
+
+    for (bits, markers) in vec![(red, &mut cell.markers_red), (black, &mut cell.markers_black)] {
+        if let Some(bits) = bits {
+            let bits = number(bits)?;
+            if bits >= 64 {
+                return Err(());
+            }
+
+            *markers = BitField8::from_bits(bits);
+        }
+    }
+
+    Ok(cell)
+}
+
+/// Splits the leading digits of a string, if any, from the rest
+fn split_number(s: &str) -> (Option<&str>, &str) {
+    let end = s.find(|c: char| !c.is_digit(10)).unwrap_or(s.len());
+    if end == 0 {
+        (None, s)
+    } else {
+        (Some(&s[..end]), &s[end..])
+    }
+}
+
+fn number<T: FromStr>(word: &str) -> Result<T, ()> {
+    word.parse().map_err(|_| ())
+}
+
+fn flag(word: &str) -> Result<bool, ()> {
+    match word {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(())
+    }
+}
+
+fn parse_ant(words: &[String]) -> Result<Ant, ()> {
+    match words {
+        &[ref id, ref color, ref state, ref resting, ref direction, ref has_food, ref position, ref alive] => {
+            let color = match &color[..] {
+                "red" => AntColor::Red,
+                "black" => AntColor::Black,
+                _ => return Err(())
+            };
+
+            let direction: u8 = number(direction)?;
+            if direction >= 6 {
+                return Err(());
+            }
+
+            Ok(Ant {
+                color,
+                id: number::<AntId>(id)?,
+                state: number::<AntState>(state)?,
+                resting: number(resting)?,
+                direction: AntDirection::from_u8(direction),
+                has_food: flag(has_food)?,
+                position: number(position)?,
+                alive: flag(alive)?
+            })
+        }
+        _ => Err(())
+    }
+}
+
+/// Checks that the simulator can run the game: the perimeter of the world must be rocky, living
+/// ants must be on clear cells without sharing them and their states must exist in the brains of
+/// their teams
+///
+/// Unlike in the world format, cells may have more than 9 units of food.
+fn validate(world: &World, ants: &[Ant], red: &[Instruction], black: &[Instruction]) -> Result<(), String> {
+    for (i, cell) in world.cells.iter().enumerate() {
+        let (x, y) = World::index_to_coords(world.width, i);
+        let on_border = x == 0 || y == 0 || x == world.width - 1 || y == world.height - 1;
+        if on_border && !cell.is_rocky {
+            return Err(format!("({}, {}): border cell is not rocky", x, y));
+        }
+    }
+
+    for (name, instrs) in vec![("red", red), ("black", black)] {
+        if let Some(error) = analysis::analyze(instrs).into_iter().filter(|d| d.is_error()).next() {
+            return Err(format!("{} brain: {}", name, error));
+        }
+    }
+
+    let mut occupied = vec![false; world.cells.len()];
+    for ant in ants.iter().filter(|ant| ant.alive) {
+        if ant.position >= world.cells.len() || world.cells[ant.position].is_rocky {
+            return Err(format!("ant {} is not on a clear cell", ant.id));
+        }
+
+        if occupied[ant.position] {
+            return Err(format!("ant {} shares its cell with another ant", ant.id));
+        }
+
+        occupied[ant.position] = true;
+
+        let states = match ant.color {
+            AntColor::Red => red.len(),
+            AntColor::Black => black.len()
+        };
+
+        if ant.state as usize >= states {
+            return Err(format!("ant {} is in state {}, which doesn't exist", ant.id, ant.state));
+        }
+    }
+
+    Ok(())
+}
@@ -1,7 +1,19 @@
-use ant::AntDirection;
-use simulator::Simulator;
-use world::World;
+use std::io::{self, Cursor};
+
+use analysis::{analyze, Diagnostic, DiagnosticKind};
+use ant::{AntColor, AntDirection};
+use compiler::{self, CompileErrorKind};
+use dump;
+use evolve::{self, Evolution};
+use generator::WorldParams;
+use instruction::{Instruction, SyntaxError, SyntaxErrorKind};
+use optimizer::optimize;
+use simulator::{Outcome, Simulator};
+use snapshot::Snapshot;
+use util::Rng;
+use world::{Violation, World};
 use test_data::{ant1, default_program, sample0};
+use tournament::{self, Arena, Entrant, Tournament};
 
 #[test]
 fn test_parse_instructions() {
@@ -9,6 +21,228 @@ fn test_parse_instructions() {
     assert_eq!(instrs.len(), 2333)
 }
 
+#[test]
+fn test_parse_instruction_errors() {
+    let source = "Sense Ahead 1 3 Food\nMove 2 10000\nFlip 3 4\n  Jump 0\nSense Ahead 1 2 Marker 6\nFlip 65536 0 0\n";
+    let errors = Instruction::parse(Cursor::new(source)).unwrap_err().errors;
+
+    let error = |line, column, token: &str, kind| SyntaxError { line, column, token: token.to_string(), kind };
+    assert_eq!(errors, vec![
+        error(2, 8, "10000", SyntaxErrorKind::InvalidState),
+        error(3, 8, "4", SyntaxErrorKind::MissingOperand),
+        error(4, 3, "Jump", SyntaxErrorKind::UnknownInstruction),
+        error(5, 24, "6", SyntaxErrorKind::InvalidMarker),
+        error(6, 6, "65536", SyntaxErrorKind::InvalidChance),
+    ]);
+}
+
+#[test]
+fn test_parse_too_many_states() {
+    let program = |len| "Drop 0\n".repeat(len);
+    assert_eq!(Instruction::parse(Cursor::new(program(9_999))).unwrap().len(), 9_999);
+
+    // Only the first instruction past the limit is reported
+    let too_many = SyntaxError { line: 10_000, column: 1, token: String::new(), kind: SyntaxErrorKind::TooManyStates };
+    for &len in &[10_000, 10_001] {
+        assert_eq!(Instruction::parse(Cursor::new(program(len))).unwrap_err().errors, vec![too_many.clone()]);
+    }
+
+    // A state past the last one can't be the target of a jump
+    let errors = Instruction::parse(Cursor::new("Drop 9998\nDrop 9999\n")).unwrap_err().errors;
+    assert_eq!(errors, vec![SyntaxError { line: 2, column: 6, token: "9999".to_string(), kind: SyntaxErrorKind::InvalidState }]);
+}
+
+#[test]
+fn test_parse_instructions_case_insensitive() {
+    let source = "sense leftahead 1 0 marker 3 ; a comment\nTURN RIGHT 0\n";
+    let instrs = Instruction::parse(Cursor::new(source)).unwrap();
+    assert_eq!(instrs.len(), 2);
+}
+
+#[test]
+fn test_parse_labelled_instructions() {
+    // Blank lines and comments may appear anywhere
+    let source = "
+; Looks for food
+start:
+    Sense Ahead take turn Food
+
+take: Move pick start
+pick: PickUp start start
+
+turn:
+    Turn Left 0
+";
+    assert_eq!(program(source), program("Sense Ahead 1 3 Food\nMove 2 0\nPickUp 0 0\nTurn Left 0\n"));
+
+    let source = "a: Move a b\n1a: Drop a\na: Drop c\nTurn Left a\nend:\n";
+    let errors = Instruction::parse(Cursor::new(source)).unwrap_err().errors;
+    let kinds: Vec<_> = errors.iter().map(|e| (e.line, e.kind)).collect();
+    assert_eq!(kinds, vec![
+        (1, SyntaxErrorKind::UndefinedLabel),
+        (2, SyntaxErrorKind::InvalidLabel),
+        (3, SyntaxErrorKind::DuplicateLabel),
+        (3, SyntaxErrorKind::UndefinedLabel),
+        (5, SyntaxErrorKind::DanglingLabel),
+    ]);
+}
+
+#[test]
+fn test_disassemble_instructions() {
+    let mut listing = Vec::new();
+    Instruction::disassemble(&default_program(), &mut listing).unwrap();
+    assert!(String::from_utf8(listing.clone()).unwrap().starts_with("start:\n    Sense Ahead L1 L3 Food\nL1:\n    Move L2 start\n"));
+    assert_eq!(program(&String::from_utf8(listing).unwrap()), default_program());
+
+    let mut listing = Vec::new();
+    Instruction::disassemble(&ant1(), &mut listing).unwrap();
+    assert_eq!(Instruction::parse(Cursor::new(listing)).unwrap(), ant1());
+}
+
+#[test]
+fn test_analyze_instructions() {
+    // ant1 contains some dead code, but nothing that would make the simulator panic
+    assert!(analyze(&ant1()).iter().all(|d| !d.is_error()));
+    assert_eq!(analyze(&default_program()), vec![]);
+
+    let source = "Flip 0 1 4\nMove 0 1\nTurn Left 3\nDrop 2\n";
+    let instrs = Instruction::parse(Cursor::new(source)).unwrap();
+    let diagnostic = |state, kind| Diagnostic { state, kind };
+    assert_eq!(analyze(&instrs), vec![
+        diagnostic(0, DiagnosticKind::FlipZero),
+        diagnostic(0, DiagnosticKind::MissingState(4)),
+        diagnostic(2, DiagnosticKind::Unreachable),
+        diagnostic(3, DiagnosticKind::Unreachable),
+    ]);
+
+    assert_eq!(analyze(&[]), vec![diagnostic(0, DiagnosticKind::Empty)]);
+}
+
+/// The same brain as `default_program`, in the structured language
+const SAMPLE_SOURCE: &'static str = "
+; Turns randomly, or moves ahead
+proc wander {
+    loop {
+        if random(3) { turn left break }
+        else if random(2) { turn right break }
+        else if move { break }
+    }
+}
+
+main {
+  search:
+    if sense ahead food {
+        if move and pickup { goto home }
+    } else {
+        wander
+    }
+    goto search
+
+  home:
+    if sense ahead home {
+        if move { drop goto search }
+    } else {
+        wander
+    }
+    goto home
+}
+";
+
+#[test]
+fn test_compile() {
+    let instrs = compiler::compile(SAMPLE_SOURCE).unwrap();
+    assert_eq!(instrs.len(), default_program().len());
+    assert_eq!(analyze(&instrs), vec![]);
+
+    // The states are numbered differently, but the ants behave exactly the same
+    let play = |red| Simulator::new(sample0(), red, ant1(), 20_000, 1).run();
+    assert_eq!(play(instrs), play(default_program()));
+
+    let source = "main {
+        for $dir in left, right {
+            if sense ahead food { move } else { turn $dir }
+        }
+    }";
+    let listing: Vec<_> = compiler::compile(source).unwrap().iter().map(|i| i.to_string()).collect();
+    assert_eq!(listing, vec![
+        "Sense Ahead 1 2 Food",
+        "Move 3 3",
+        "Turn Left 3",
+        "Sense Ahead 4 5 Food",
+        "Move 0 0",
+        "Turn Right 0"
+    ]);
+}
+
+#[test]
+fn test_compile_errors() {
+    let error = |source: &str| compiler::compile(source).unwrap_err();
+
+    let e = error("main {\n    goto nowhere\n}");
+    assert_eq!((e.line, e.column, e.kind), (2, 10, CompileErrorKind::UndefinedLabel));
+    assert_eq!(e.to_string(), "2:10: undefined label `nowhere`");
+
+    assert_eq!(error("main { loop { } }").kind, CompileErrorKind::EmptyLoop);
+    assert_eq!(error("main { break }").kind, CompileErrorKind::BreakOutsideLoop);
+    assert_eq!(error("main { a: a: drop }").kind, CompileErrorKind::DuplicateLabel);
+    assert_eq!(error("main { mark 6 }").kind, CompileErrorKind::InvalidMarker);
+    assert_eq!(error("main { turn $dir }").kind, CompileErrorKind::UnboundVariable);
+    assert_eq!(error("main { if sense ahead food { drop }").kind, CompileErrorKind::UnexpectedEnd);
+    assert_eq!(error("proc f($x) { turn $x } main { f(left, right) }").kind, CompileErrorKind::WrongArity);
+    assert_eq!(error("proc a { b } proc b { a } main { a }").kind, CompileErrorKind::RecursiveProcedure);
+    assert_eq!(error("proc a { drop }").kind, CompileErrorKind::MissingMain);
+
+    let source = format!("main {{ {} }}", "turn left ".repeat(10_001));
+    assert_eq!(error(&source).kind, CompileErrorKind::TooManyStates);
+}
+
+#[test]
+fn test_mutate_flip_bounds() {
+    // Tweaking the odds of a `Flip` must not wrap around to 0
+    for seed in 0..200 {
+        let mut rng = Rng::new(seed);
+        for &p in &[1, 2, 65534, 65535] {
+            let mut instrs = vec![Instruction::Flip(p, 0, 0)];
+            evolve::mutate(&mut instrs, &mut rng);
+            match instrs[0] {
+                Instruction::Flip(q, _, _) => assert!(q >= 1 && (q as i64 - p as i64).abs() <= 2, "{} mutated to {}", p, q),
+                ref instr => panic!("unexpected instruction {}", instr)
+            }
+
+            assert_eq!(analyze(&instrs), vec![]);
+        }
+    }
+}
+
+#[test]
+fn test_optimize() {
+    // States 1 and 2 are equivalent and 3 is only reachable through the second state of a `Flip 1`
+    let instrs = program("Sense Ahead 1 4 Food\nMove 0 2\nMove 0 1\nDrop 0\nFlip 1 5 3\nTurn Left 0\n");
+    let optimized = optimize(&instrs).unwrap();
+    assert_eq!(optimized, program("Sense Ahead 1 2 Food\nMove 0 1\nFlip 1 3 3\nTurn Left 0\n"));
+
+    // Optimizing again doesn't change anything
+    assert_eq!(optimize(&optimized), Ok(optimized));
+    assert_eq!(optimize(&default_program()), Ok(default_program()));
+
+    // Programs with errors are rejected instead of optimized
+    let broken = program("Flip 0 1 2\nMove 0 0\n");
+    assert_eq!(optimize(&broken), Err(vec![
+        Diagnostic { state: 0, kind: DiagnosticKind::FlipZero },
+        Diagnostic { state: 0, kind: DiagnosticKind::MissingState(2) },
+    ]));
+
+    let optimized = optimize(&ant1()).unwrap();
+    assert!(optimized.len() < ant1().len());
+    assert_eq!(analyze(&optimized), vec![]);
+
+    // The optimized program must result in exactly the same games
+    for &seed in &[1, 42, 777, 12345, 99_999] {
+        let play = |red| Simulator::new(sample0(), red, default_program(), 3000, seed).run();
+        assert_eq!(play(optimized.clone()), play(ant1()));
+    }
+}
+
 #[test]
 fn test_parse_world() {
     let world = sample0();
@@ -19,6 +253,130 @@ fn test_parse_world() {
     assert_eq!(world.count_rocks(), 850);
 }
 
+#[test]
+fn test_parse_world_violations() {
+    let source = "4\n4\n# # # #\n # . 12 #\n# x . .\n# # # #\n";
+    let violations = World::parse(Cursor::new(source)).unwrap_err().violations;
+    assert_eq!(violations, vec![
+        Violation::InvalidCell { x: 1, y: 2, token: "x".to_string() },
+        Violation::Indentation { y: 3 },
+        Violation::FoodOutOfRange { x: 2, y: 1, food: 12 },
+        Violation::NonRockyBorder { x: 3, y: 2 },
+    ]);
+}
+
+#[test]
+fn test_parse_world_cell_count() {
+    let source = "3\n3\n# # #\n # . #\n";
+    let violations = World::parse(Cursor::new(source)).unwrap_err().violations;
+    assert_eq!(violations, vec![Violation::CellCount { expected: 9, found: 6 }]);
+
+    // A row that is too long and one that is too short don't cancel out
+    let source = "3\n3\n# # # #\n # .\n# # #\n";
+    let violations = World::parse(Cursor::new(source)).unwrap_err().violations;
+    assert_eq!(violations, vec![
+        Violation::RowWidth { y: 0, expected: 3, found: 4 },
+        Violation::RowWidth { y: 1, expected: 3, found: 2 },
+        Violation::NonRockyBorder { x: 2, y: 1 },
+    ]);
+
+    let violations = World::parse(Cursor::new("3\nthree\n")).unwrap_err().violations;
+    assert_eq!(violations, vec![Violation::InvalidDimension { line: 2, token: "three".to_string() }]);
+}
+
+#[test]
+fn test_generate_world() {
+    let params = WorldParams::default();
+    let world = World::generate(1, &params).unwrap();
+    assert_eq!((world.width, world.height), (100, 100));
+    assert_eq!(world.validate(), Ok(()));
+
+    // Two hexagonal anthills of side 7
+    let red_anthill = world.cells.iter().filter(|c| c.anthill == Some(AntColor::Red)).count();
+    let black_anthill = world.cells.iter().filter(|c| c.anthill == Some(AntColor::Black)).count();
+    assert_eq!((red_anthill, black_anthill), (127, 127));
+
+    // 11 food blobs of 5x5 cells, with 5 units of food each
+    assert_eq!(world.count_food(), 11 * 25 * 5);
+    assert!(world.count_rocks() as usize >= 4 * 99 + 14);
+
+    // No two elements touch each other
+    for (i, cell) in world.cells.iter().enumerate() {
+        let (x, y) = World::index_to_coords(world.width, i);
+        if x == 0 || y == 0 || x == world.width - 1 || y == world.height - 1 || cell.food == 0 && cell.anthill.is_none() {
+            continue;
+        }
+
+        for dir in AntDirection::all() {
+            let adjacent = &world.cells[World::adjacent_position(world.width, i, dir)];
+            assert!(!adjacent.is_rocky);
+            assert!(adjacent.anthill.is_none() || adjacent.anthill == cell.anthill);
+            assert!(adjacent.food == 0 || cell.anthill.is_none());
+        }
+    }
+
+    // The same seed always results in the same world
+    assert_eq!(World::generate(1, &params).unwrap().cells, world.cells);
+    assert!(World::generate(2, &params).unwrap().cells != world.cells);
+
+    // Elements that can't fit
+    let crowded = WorldParams { width: 20, height: 20, ..WorldParams::default() };
+    assert!(World::generate(1, &crowded).is_none());
+}
+
+#[test]
+fn test_write_world() {
+    let mut output = Vec::new();
+    sample0().write_to(&mut output).unwrap();
+    let world = World::parse(Cursor::new(&output)).unwrap();
+    assert_eq!(world.cells, sample0().cells);
+
+    let generated = World::generate(7, &WorldParams::default()).unwrap();
+    let mut output = Vec::new();
+    generated.write_to(&mut output).unwrap();
+    assert_eq!(World::parse(Cursor::new(&output)).unwrap().cells, generated.cells);
+
+    let mut too_much_food = tiny_world();
+    too_much_food.cells[World::coords_to_index(5, 2, 1)].food = 10;
+    assert!(too_much_food.write_to(Vec::new()).is_err());
+}
+
+#[test]
+fn test_starting_world() {
+    let mut simulator = Simulator::new(sample0(), ant1(), ant1(), 100_000, 12345);
+    simulator.run_rounds(10_000);
+
+    // Food in the anthills is discarded, food carried by ants is kept
+    let world = &simulator.world;
+    let carried = world.ants.iter().filter(|ant| ant.alive && ant.has_food).count() as u16;
+    let anthill_food = world.count_red_food() + world.count_black_food();
+    let starting = world.to_starting_world();
+    assert_eq!(starting.count_food(), world.count_food() - anthill_food + carried);
+    assert_eq!(starting.count_ants(), 0);
+    assert!(starting.cells.iter().all(|c| !c.markers_red.any() && !c.markers_black.any()));
+
+    // The starting world can be written and read back, unlike the original world
+    assert!(world.write_to(Vec::new()).is_err());
+    let mut output = Vec::new();
+    starting.write_to(&mut output).unwrap();
+    assert_eq!(World::parse(Cursor::new(&output)).unwrap().cells, starting.cells);
+}
+
+#[test]
+fn test_starting_world_spills_food() {
+    let mut world = tiny_world();
+    world.cells[World::coords_to_index(5, 2, 1)].food = 25;
+    let food: Vec<_> = world.to_starting_world().cells.iter().map(|c| c.food).collect();
+
+    // The anthills don't get any food
+    assert_eq!(food, vec![
+        0, 0, 0, 0, 0,
+        0, 0, 9, 9, 0,
+        0, 0, 0, 7, 0,
+        0, 0, 0, 0, 0,
+    ]);
+}
+
 #[test]
 fn test_adjacent_position() {
     use self::AntDirection::*;
@@ -47,15 +405,13 @@ fn test_adjacent_position() {
 #[test]
 fn test_create_simulator() {
     let world = sample0();
-    let simulator = Simulator::new(world, ant1(), ant1(), 100_000);
-    assert_eq!(simulator.ants.len(), 182);
-
-    // The ids are stored in ascending order in the positions vector
-    let get_id = |i: usize| simulator.world.cells[i].ant.as_ref().unwrap().id;
-    let mut prev_id = get_id(simulator.ants[0]);
-    for &ant in &simulator.ants[1..] {
-        assert!(get_id(ant) > prev_id);
-        prev_id = get_id(ant);
+    let simulator = Simulator::new(world, ant1(), ant1(), 100_000, 12345);
+    assert_eq!(simulator.world.ants.len(), 182);
+
+    // Ants are indexed by id, and their cells point back to them
+    for (i, ant) in simulator.world.ants.iter().enumerate() {
+        assert_eq!(ant.id as usize, i);
+        assert_eq!(simulator.world.cells[ant.position].ant, Some(ant.id));
     }
 }
 
@@ -63,10 +419,595 @@ fn test_create_simulator() {
 fn test_run_simulator() {
     let world = sample0();
     let instr = default_program();
-    let mut simulator = Simulator::new(world, instr.clone(), instr, 100_000);
+    let mut simulator = Simulator::new(world, instr.clone(), instr, 100_000, 12345);
     assert_eq!(simulator.world.count_ants(), 182);
     simulator.run_rounds(10_000);
     let outcome = simulator.partial_outcome();
     assert_eq!(outcome.red_score, 0);
     assert_eq!(outcome.black_score, 7);
 }
+
+fn program(source: &str) -> Vec<Instruction> {
+    Instruction::parse(Cursor::new(source)).unwrap()
+}
+
+fn world(source: &str) -> World {
+    World::parse(Cursor::new(source)).unwrap()
+}
+
+/// A small world with two anthills of seven cells and some food in between
+fn arena() -> World {
+    world("16
+12
+# # # # # # # # # # # # # # # #
+ # . . . . . . . . . . . . . . #
+# . . + + . . . . . . 5 5 . . #
+ # . + + + . . . . . . 5 5 . . #
+# . . + + . . # # . . . . . . #
+ # . . . . . . # . . . . . . . #
+# . . . . . . # . . . . . . . #
+ # . 9 9 . . . . . . . - - . . #
+# . . 9 9 . . . . . . - - - . #
+ # . . . . . . . . . . - - . . #
+# . . . . . . . . . . . . . . #
+ # # # # # # # # # # # # # # # #
+")
+}
+
+fn outcome(red_score: u16, red_alive: u16, black_score: u16, black_alive: u16, food_left: u16, round: u32) -> Outcome {
+    Outcome { red_score, red_alive, black_score, black_alive, food_left, round }
+}
+
+#[test]
+fn test_golden_outcomes() {
+    // These outcomes were produced by the simulator itself. They are here to catch any change of
+    // behavior, for instance when optimizing the simulator.
+    let play = |world: World, red: Vec<Instruction>, black: Vec<Instruction>, rounds, seed| {
+        Simulator::new(world, red, black, rounds, seed).run()
+    };
+
+    assert_eq!(play(sample0(), ant1(), ant1(), 10_000, 12345), outcome(104, 83, 74, 75, 500, 10_000));
+    assert_eq!(play(sample0(), ant1(), ant1(), 10_000, 42), outcome(87, 85, 108, 90, 453, 10_000));
+    assert_eq!(play(sample0(), default_program(), ant1(), 20_000, 1), outcome(5, 82, 186, 87, 478, 20_000));
+    assert_eq!(play(arena(), ant1(), ant1(), 10_000, 42), outcome(0, 7, 9, 7, 56, 10_000));
+    assert_eq!(play(arena(), ant1(), default_program(), 10_000, 12345), outcome(16, 7, 6, 3, 65, 10_000));
+    assert_eq!(play(arena(), ant1(), default_program(), 20_000, 1), outcome(4, 7, 9, 6, 57, 20_000));
+
+    let generated = World::generate(3, &WorldParams::default()).unwrap();
+    assert_eq!(play(generated.clone(), ant1(), ant1(), 10_000, 12345), outcome(142, 124, 145, 119, 1340, 10_000));
+    assert_eq!(play(generated, default_program(), ant1(), 10_000, 7), outcome(7, 117, 199, 125, 1307, 10_000));
+}
+
+#[test]
+fn test_simulator_snapshot() {
+    let mut simulator = Simulator::new(sample0(), ant1(), default_program(), 10_000, 42);
+    simulator.run_rounds(3_000);
+
+    // The snapshot survives a round trip through the file format
+    let mut written = Vec::new();
+    simulator.snapshot().write_to(&mut written).unwrap();
+    let snapshot = Snapshot::parse(Cursor::new(&written)).unwrap();
+    let mut rewritten = Vec::new();
+    snapshot.write_to(&mut rewritten).unwrap();
+    assert_eq!(String::from_utf8(rewritten).unwrap(), String::from_utf8(written).unwrap());
+
+    // And the restored game continues exactly like the original one
+    let mut restored = Simulator::restore(snapshot);
+    assert_eq!((restored.round, restored.seed), (3_000, 42));
+    let outcome = simulator.run();
+    assert_eq!(restored.run(), outcome);
+
+    let (mut expected, mut actual) = (Vec::new(), Vec::new());
+    simulator.dump(&mut expected).unwrap();
+    restored.dump(&mut actual).unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_simulator_state() {
+    let simulator = || Simulator::new(sample0(), ant1(), default_program(), 2_000, 42);
+    let mut straight = simulator();
+    let outcome = straight.run();
+
+    let mut rewound = simulator();
+    rewound.run_rounds(500);
+    let state = rewound.state();
+    rewound.run_rounds(700);
+
+    // Going back replays the rest of the game exactly
+    rewound.set_state(&state);
+    assert_eq!(rewound.round, 500);
+    assert_eq!(rewound.run(), outcome);
+
+    let (mut expected, mut actual) = (Vec::new(), Vec::new());
+    straight.dump(&mut expected).unwrap();
+    rewound.dump(&mut actual).unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_simulator_snapshot_errors() {
+    let mut written = Vec::new();
+    Simulator::new(arena(), ant1(), ant1(), 100, 42).snapshot().write_to(&mut written).unwrap();
+    let source = String::from_utf8(written).unwrap();
+    let parse = |source: &str| Snapshot::parse(Cursor::new(source)).unwrap_err().to_string();
+
+    assert_eq!(parse(&source.replace("rng", "seed")), "invalid snapshot: line 5: expected `rng VALUE`");
+    assert_eq!(parse(&source.replace(" . . . . . . . . . . . . . . #", " . . . . . . . . . . . . . . x")),
+               "invalid snapshot: line 8: invalid cell `x`");
+    assert_eq!(parse(&source.replace("0 red 0 0 0 0 35 1", "0 red 0 0 0 0 0 1")),
+               "invalid snapshot: ant 0 is not on a clear cell");
+    assert_eq!(parse(&source.replace("0 red 0 0 0 0 35 1", "0 red 9999 0 0 0 37 1")),
+               "invalid snapshot: ant 0 is in state 9999, which doesn't exist");
+    let truncated: String = source.lines().take(30).map(|line| format!("{}\n", line)).collect();
+    assert_eq!(parse(&truncated), "invalid snapshot: line 31: unexpected end of file");
+}
+
+#[test]
+fn test_tournament() {
+    let entrant = |name: &str, instructions| Entrant { name: name.to_string(), instructions };
+    let tournament = Tournament {
+        entrants: vec![entrant("ant1", ant1()), entrant("sample", default_program()), entrant("idle", program("Turn Left 0\n"))],
+        arenas: vec![Arena { name: "arena".to_string(), world: arena() }],
+        rounds: 3000,
+        seed: 42
+    };
+
+    // Three entrants on one world play each other twice
+    let results = tournament.run(3);
+    assert_eq!(results.len(), 6);
+    for (result, pairing) in results.iter().zip(tournament.pairings()) {
+        assert_eq!(result.pairing, pairing);
+        assert_eq!(result.outcome, tournament.play(pairing));
+    }
+
+    // The results don't depend on the amount of threads
+    assert_eq!(tournament.run(1), results);
+
+    let standings = tournament::standings(3, &results);
+    let total_points: u32 = standings.iter().map(|s| s.points).sum();
+    assert_eq!(total_points, 2 * 6);
+    assert!(standings.windows(2).all(|w| w[0].points >= w[1].points));
+    assert_eq!(standings[2].entrant, 2);
+}
+
+/// A world with a single red ant at (1, 1) facing a cell with food, and a black ant right below
+/// the cell with food
+fn tiny_world() -> World {
+    world("5\n4\n# # # # #\n # + 1 . #\n# . - . #\n # # # # #\n")
+}
+
+#[test]
+fn test_evolve() {
+    let params = evolve::Params { population: 4, elites: 1, worlds: 1, rounds: 300, ..evolve::Params::default() };
+    let evolution = || Evolution::new(params.clone(), vec![default_program()], vec![default_program()], 42);
+
+    // The results don't depend on the amount of threads
+    let (mut single, mut multi) = (evolution(), evolution());
+    let ranked = single.step(1).unwrap();
+    assert_eq!(multi.step(4).unwrap(), ranked);
+    assert_eq!(single.population, multi.population);
+    assert_eq!(single.generation, 1);
+
+    // Offspring are valid programs and the best program survives
+    assert!(single.population.iter().all(|p| analyze(p).iter().all(|d| !d.is_error())));
+    assert!(single.population.contains(&ranked[0].instructions));
+    assert!(ranked.windows(2).all(|w| w[0].fitness >= w[1].fitness));
+
+    // Resuming from a checkpoint gives the same results as not stopping
+    let mut checkpoint = Vec::new();
+    single.write_checkpoint(&mut checkpoint).unwrap();
+    let mut resumed = Evolution::read_checkpoint(Cursor::new(checkpoint), params.clone(), vec![default_program()]).unwrap();
+    assert_eq!((resumed.seed, resumed.generation), (42, 1));
+    assert_eq!(resumed.population, single.population);
+    assert_eq!(resumed.step(2).unwrap(), single.step(2).unwrap());
+    assert_eq!(resumed.population, single.population);
+
+    assert!(Evolution::read_checkpoint(Cursor::new("seed 42\ngeneration 1\nbrain 2\nDrop 0\n"), params.clone(), vec![]).is_err());
+
+    // Checkpoints with no brains, the wrong amount of brains or brains that would crash
+    let checkpoints = [
+        "seed 42\ngeneration 1\n".to_string(),
+        "seed 42\ngeneration 1\nbrain 1\nDrop 0\n".to_string(),
+        format!("seed 42\ngeneration 1\n{}", "brain 1\nDrop 0\n".repeat(3) + "brain 1\nDrop 1\n"),
+        format!("seed 42\ngeneration 1\n{}", "brain 1\nDrop 0\n".repeat(5)),
+    ];
+    for checkpoint in &checkpoints {
+        let error = Evolution::read_checkpoint(Cursor::new(checkpoint), params.clone(), vec![]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    let valid = format!("seed 42\ngeneration 1\n{}", "brain 1\nDrop 0\n".repeat(4));
+    assert!(Evolution::read_checkpoint(Cursor::new(valid), params, vec![]).is_ok());
+}
+
+#[test]
+fn test_evolve_world_params() {
+    let params = evolve::Params { population: 2, worlds: 1, rounds: 10, ..evolve::Params::default() };
+    assert!(params.validate().is_ok());
+
+    // The anthills can never fit, so no world is generated
+    let world_params = WorldParams { width: 12, height: 12, ..WorldParams::default() };
+    let params = evolve::Params { world_params, ..params };
+    assert_eq!(params.validate(), Err(evolve::NoWorld { seed: 0 }));
+
+    let mut evolution = Evolution::new(params, vec![default_program()], vec![default_program()], 42);
+    assert!(evolution.step(1).is_err());
+    assert_eq!(evolution.generation, 0);
+    assert_eq!(evolution.population.len(), 2);
+}
+
+#[test]
+fn test_mark_unmark() {
+    let mut simulator = Simulator::new(tiny_world(), program("Mark 1 1\nMark 5 2\nUnmark 1 3\nUnmark 4 3\n"), program("Turn Left 0\n"), 100, 1);
+    let red_cell = World::coords_to_index(5, 1, 1);
+
+    simulator.run_rounds(2);
+    let markers: Vec<_> = simulator.world.cells[red_cell].markers(AntColor::Red).iter().collect();
+    assert_eq!(markers, vec![1, 5]);
+    assert!(!simulator.world.cells[red_cell].markers(AntColor::Black).any());
+
+    // Unmarking a marker that isn't set does nothing
+    simulator.run_rounds(2);
+    let markers: Vec<_> = simulator.world.cells[red_cell].markers(AntColor::Red).iter().collect();
+    assert_eq!(markers, vec![5]);
+}
+
+#[test]
+fn test_pickup_drop() {
+    let red = program("PickUp 1 5\nPickUp 5 2\nDrop 3\nDrop 4\nPickUp 5 4\nTurn Left 5\n");
+    let mut simulator = Simulator::new(tiny_world(), red, program("Turn Left 0\n"), 100, 1);
+    let red_cell = World::coords_to_index(5, 1, 1);
+    simulator.world.cells[red_cell].food = 1;
+    let red_ant = |simulator: &Simulator| simulator.world.ant_at(red_cell).unwrap().clone();
+
+    // Picking up succeeds
+    simulator.one_round();
+    assert_eq!((red_ant(&simulator).state, red_ant(&simulator).has_food), (1, true));
+    assert_eq!(simulator.world.cells[red_cell].food, 0);
+
+    // Picking up fails when the ant is already carrying food
+    simulator.one_round();
+    assert_eq!(red_ant(&simulator).state, 2);
+
+    // Dropping puts the food back in the cell
+    simulator.one_round();
+    assert_eq!((red_ant(&simulator).state, red_ant(&simulator).has_food), (3, false));
+    assert_eq!(simulator.world.cells[red_cell].food, 1);
+
+    // Dropping without food changes nothing but the state
+    simulator.one_round();
+    assert_eq!(red_ant(&simulator).state, 4);
+    assert_eq!(simulator.world.cells[red_cell].food, 1);
+
+    // Picking up fails when there is no food in the cell
+    simulator.world.cells[red_cell].food = 0;
+    simulator.one_round();
+    assert_eq!((red_ant(&simulator).state, red_ant(&simulator).has_food), (4, false));
+}
+
+#[test]
+fn test_move() {
+    // The first move succeeds, after which the ant rests for 14 rounds
+    let red = program("Move 1 3\nTurn Left 2\nMove 3 4\nTurn Left 3\nTurn Left 4\n");
+    let mut simulator = Simulator::new(tiny_world(), red, program("Turn Left 0\n"), 100, 1);
+    simulator.one_round();
+    let new_cell = World::coords_to_index(5, 2, 1);
+    assert!(simulator.world.cells[World::coords_to_index(5, 1, 1)].ant.is_none());
+    assert_eq!(simulator.world.ant_at(new_cell).unwrap().resting, 14);
+    assert_eq!(simulator.world.ant_at(new_cell).unwrap().state, 1);
+
+    // Turn left, so the ant faces up-right, where there is a rock
+    simulator.run_rounds(15);
+    assert_eq!(simulator.world.ant_at(new_cell).unwrap().direction, AntDirection::UpRight);
+    simulator.one_round();
+    assert_eq!(simulator.world.ant_at(new_cell).unwrap().state, 4);
+    assert_eq!(simulator.world.ant_at(new_cell).unwrap().resting, 0);
+
+    // Moving into another ant fails too
+    let red = program("Turn Right 1\nMove 2 3\nTurn Left 2\nTurn Left 3\n");
+    let mut simulator = Simulator::new(tiny_world(), red, program("Turn Left 0\n"), 100, 1);
+    simulator.run_rounds(2);
+    let red_ant = simulator.world.ant_at(World::coords_to_index(5, 1, 1)).unwrap().clone();
+    assert_eq!(red_ant.state, 3);
+}
+
+#[test]
+fn test_kill_surrounded_ant() {
+    // The black ant is surrounded by four red ants, the fifth one moves next to it
+    let world = world("7
+6
+# # # # # # #
+ # . . . . . #
+# . + . . . #
+ # . + - + . #
+# . . + + . #
+ # # # # # # #
+");
+
+    let mut simulator = Simulator::new(world, program("Move 1 1\nTurn Left 1\n"), program("Turn Left 0\n"), 100, 1);
+    let black_cell = World::coords_to_index(7, 3, 3);
+    simulator.world.ant_at_mut(black_cell).unwrap().has_food = true;
+
+    // Only the red ant at (2, 2) may move
+    for &(x, y) in &[(2, 3), (4, 3), (3, 4), (4, 4)] {
+        simulator.world.ant_at_mut(World::coords_to_index(7, x, y)).unwrap().resting = 1;
+    }
+    assert_eq!(simulator.world.count_black_ants(), 1);
+
+    simulator.one_round();
+    assert_eq!(simulator.world.count_black_ants(), 0);
+    assert_eq!(simulator.world.count_red_ants(), 5);
+
+    // A dead ant leaves 3 units of food, plus the one it was carrying
+    assert!(simulator.world.cells[black_cell].ant.is_none());
+    assert_eq!(simulator.world.cells[black_cell].food, 4);
+    assert_eq!(simulator.partial_outcome().black_score, 4);
+}
+
+#[test]
+fn test_one_round_for() {
+    // The red ant at (2, 4) surrounds the black ant, which has a lower id and acts before dying
+    let world = world("7
+6
+# # # # # # #
+ # . . . . . #
+# . . + + . #
+ # . + - + . #
+# . + . . . #
+ # # # # # # #
+");
+
+    let mut simulator = Simulator::new(world, program("Move 1 1\nTurn Left 1\n"), program("Turn Left 0\n"), 100, 1);
+    for &(x, y) in &[(3, 2), (4, 2), (2, 3), (4, 3)] {
+        simulator.world.ant_at_mut(World::coords_to_index(7, x, y)).unwrap().resting = 2;
+    }
+
+    let black = simulator.world.ant_at(World::coords_to_index(7, 3, 3)).unwrap().id as usize;
+    assert!(simulator.one_round_for(black));
+    assert!(!simulator.world.ants[black].alive);
+
+    // Resting and dead ants don't act
+    assert!(!simulator.one_round_for(0));
+    assert!(!simulator.one_round_for(black));
+}
+
+const TINY_DUMP: &'static str = "random seed: 12345
+
+After round 0...
+cell (0, 0): rock
+cell (1, 0): rock
+cell (2, 0): rock
+cell (3, 0): rock
+cell (4, 0): rock
+cell (0, 1): rock
+cell (1, 1): red hill; red ant of id 0, dir 0, food 0, state 0, resting 0
+cell (2, 1): 1 food; 
+cell (3, 1): 
+cell (4, 1): rock
+cell (0, 2): rock
+cell (1, 2): 
+cell (2, 2): black hill; black ant of id 1, dir 0, food 0, state 0, resting 0
+cell (3, 2): 
+cell (4, 2): rock
+cell (0, 3): rock
+cell (1, 3): rock
+cell (2, 3): rock
+cell (3, 3): rock
+cell (4, 3): rock
+
+After round 1...
+cell (0, 0): rock
+cell (1, 0): rock
+cell (2, 0): rock
+cell (3, 0): rock
+cell (4, 0): rock
+cell (0, 1): rock
+cell (1, 1): red hill; red ant of id 0, dir 0, food 0, state 1, resting 0
+cell (2, 1): 1 food; 
+cell (3, 1): 
+cell (4, 1): rock
+cell (0, 2): rock
+cell (1, 2): 
+cell (2, 2): black hill; black marks: 2; black ant of id 1, dir 0, food 0, state 1, resting 0
+cell (3, 2): 
+cell (4, 2): rock
+cell (0, 3): rock
+cell (1, 3): rock
+cell (2, 3): rock
+cell (3, 3): rock
+cell (4, 3): rock
+
+After round 2...
+cell (0, 0): rock
+cell (1, 0): rock
+cell (2, 0): rock
+cell (3, 0): rock
+cell (4, 0): rock
+cell (0, 1): rock
+cell (1, 1): red hill; 
+cell (2, 1): 1 food; red ant of id 0, dir 0, food 0, state 2, resting 14
+cell (3, 1): 
+cell (4, 1): rock
+cell (0, 2): rock
+cell (1, 2): 
+cell (2, 2): black hill; black marks: 24; black ant of id 1, dir 0, food 0, state 1, resting 0
+cell (3, 2): 
+cell (4, 2): rock
+cell (0, 3): rock
+cell (1, 3): rock
+cell (2, 3): rock
+cell (3, 3): rock
+cell (4, 3): rock
+";
+
+fn tiny_simulator() -> Simulator {
+    Simulator::new(tiny_world(), default_program(), program("Mark 2 1\nMark 4 1\n"), 2, 12345)
+}
+
+#[test]
+fn test_dump() {
+    let mut output = Vec::new();
+    dump::dump_game(&mut output, &mut tiny_simulator()).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), TINY_DUMP);
+}
+
+#[test]
+fn test_compare_dump() {
+    assert_eq!(dump::compare(Cursor::new(TINY_DUMP), &mut tiny_simulator()), Ok(2));
+
+    let wrong_dump = TINY_DUMP.replace("state 2, resting 14", "state 2, resting 13");
+    let mismatch = dump::compare(Cursor::new(wrong_dump), &mut tiny_simulator()).unwrap_err();
+    assert_eq!(mismatch.line, 55);
+    assert_eq!(mismatch.round, 2);
+    assert_eq!(mismatch.found, Some("cell (2, 1): 1 food; red ant of id 0, dir 0, food 0, state 2, resting 14".to_string()));
+}
+
+/// A dump of four rounds on `tiny_world`, worked out by hand from the rules of the contest
+/// specification rather than produced by this simulator. The random numbers are the first ones
+/// listed in the specification for seed 12345: 7193, 2932 and 10386.
+///
+/// - Round 1: red `Flip 2` draws 7193 % 2 = 1 and goes to state 3, black `Flip 3` draws
+///   2932 % 3 = 1 and goes to state 1
+/// - Round 2: red moves onto the food and rests for 14 rounds, black sets marker 3
+/// - Round 3: red rests, black `Flip 7` draws 10386 % 7 = 5 and goes to state 4
+/// - Round 4: red rests, black turns right
+const SPEC_DUMP: &'static str = "random seed: 12345
+
+After round 0...
+cell (0, 0): rock
+cell (1, 0): rock
+cell (2, 0): rock
+cell (3, 0): rock
+cell (4, 0): rock
+cell (0, 1): rock
+cell (1, 1): red hill; red ant of id 0, dir 0, food 0, state 0, resting 0
+cell (2, 1): 1 food; 
+cell (3, 1): 
+cell (4, 1): rock
+cell (0, 2): rock
+cell (1, 2): 
+cell (2, 2): black hill; black ant of id 1, dir 0, food 0, state 0, resting 0
+cell (3, 2): 
+cell (4, 2): rock
+cell (0, 3): rock
+cell (1, 3): rock
+cell (2, 3): rock
+cell (3, 3): rock
+cell (4, 3): rock
+
+After round 1...
+cell (0, 0): rock
+cell (1, 0): rock
+cell (2, 0): rock
+cell (3, 0): rock
+cell (4, 0): rock
+cell (0, 1): rock
+cell (1, 1): red hill; red ant of id 0, dir 0, food 0, state 3, resting 0
+cell (2, 1): 1 food; 
+cell (3, 1): 
+cell (4, 1): rock
+cell (0, 2): rock
+cell (1, 2): 
+cell (2, 2): black hill; black ant of id 1, dir 0, food 0, state 1, resting 0
+cell (3, 2): 
+cell (4, 2): rock
+cell (0, 3): rock
+cell (1, 3): rock
+cell (2, 3): rock
+cell (3, 3): rock
+cell (4, 3): rock
+
+After round 2...
+cell (0, 0): rock
+cell (1, 0): rock
+cell (2, 0): rock
+cell (3, 0): rock
+cell (4, 0): rock
+cell (0, 1): rock
+cell (1, 1): red hill; 
+cell (2, 1): 1 food; red ant of id 0, dir 0, food 0, state 4, resting 14
+cell (3, 1): 
+cell (4, 1): rock
+cell (0, 2): rock
+cell (1, 2): 
+cell (2, 2): black hill; black marks: 3; black ant of id 1, dir 0, food 0, state 2, resting 0
+cell (3, 2): 
+cell (4, 2): rock
+cell (0, 3): rock
+cell (1, 3): rock
+cell (2, 3): rock
+cell (3, 3): rock
+cell (4, 3): rock
+
+After round 3...
+cell (0, 0): rock
+cell (1, 0): rock
+cell (2, 0): rock
+cell (3, 0): rock
+cell (4, 0): rock
+cell (0, 1): rock
+cell (1, 1): red hill; 
+cell (2, 1): 1 food; red ant of id 0, dir 0, food 0, state 4, resting 13
+cell (3, 1): 
+cell (4, 1): rock
+cell (0, 2): rock
+cell (1, 2): 
+cell (2, 2): black hill; black marks: 3; black ant of id 1, dir 0, food 0, state 4, resting 0
+cell (3, 2): 
+cell (4, 2): rock
+cell (0, 3): rock
+cell (1, 3): rock
+cell (2, 3): rock
+cell (3, 3): rock
+cell (4, 3): rock
+
+After round 4...
+cell (0, 0): rock
+cell (1, 0): rock
+cell (2, 0): rock
+cell (3, 0): rock
+cell (4, 0): rock
+cell (0, 1): rock
+cell (1, 1): red hill; 
+cell (2, 1): 1 food; red ant of id 0, dir 0, food 0, state 4, resting 12
+cell (3, 1): 
+cell (4, 1): rock
+cell (0, 2): rock
+cell (1, 2): 
+cell (2, 2): black hill; black marks: 3; black ant of id 1, dir 1, food 0, state 5, resting 0
+cell (3, 2): 
+cell (4, 2): rock
+cell (0, 3): rock
+cell (1, 3): rock
+cell (2, 3): rock
+cell (3, 3): rock
+cell (4, 3): rock
+";
+
+#[test]
+fn test_compare_spec_dump() {
+    let red = program("Flip 2 1 3\nTurn Left 1\nTurn Left 2\nMove 4 2\nPickUp 5 2\nTurn Left 5\n");
+    let black = program("Flip 3 0 1\nMark 3 2\nFlip 7 0 4\nTurn Left 3\nTurn Right 5\nTurn Right 5\n");
+    let mut simulator = Simulator::new(tiny_world(), red, black, 4, 12345);
+    assert_eq!(dump::compare(Cursor::new(SPEC_DUMP), &mut simulator), Ok(4));
+}
+
+/// Compares the simulator against a reference dump of `sample.ant` (`default_program`) playing
+/// against itself for 10000 rounds with seed 12345, as distributed by the contest organizers
+///
+/// Run with `ANT_DUMP=dump.all ANT_DUMP_WORLD=tiny.world cargo test -- --ignored`
+#[test]
+#[ignore]
+fn test_compare_reference_dump() {
+    use std::env;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    let open = |var| BufReader::new(File::open(env::var(var).expect(var)).unwrap());
+    let world = World::parse(open("ANT_DUMP_WORLD")).unwrap();
+    let mut simulator = Simulator::new(world, default_program(), default_program(), 10_000, 12345);
+    if let Err(mismatch) = dump::compare(open("ANT_DUMP"), &mut simulator) {
+        panic!("{}", mismatch);
+    }
+}
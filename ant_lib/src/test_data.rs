@@ -109,7 +109,7 @@ pub fn sample0() -> World {
 ";
 
     let reader = BufReader::new(Cursor::new(world_str));
-    World::parse(reader)
+    World::parse(reader).expect("Invalid sample0 world")
 }
 
 pub fn default_program() -> Vec<Instruction> {
@@ -132,7 +132,7 @@ Move 8 11
 ";
 
     let buffer = BufReader::new(Cursor::new(instrs_str));
-    Instruction::parse(buffer)
+    Instruction::parse(buffer).expect("Invalid default program")
 }
 
 pub fn ant1() -> Vec<Instruction> {
@@ -2472,5 +2472,5 @@ Mark 0 1813
 ";
 
     let reader = BufReader::new(Cursor::new(instrs_str));
-    Instruction::parse(reader)
+    Instruction::parse(reader).expect("Invalid ant1 program")
 }
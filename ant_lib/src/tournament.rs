@@ -0,0 +1,139 @@
+//! Round-robin tournaments between ant programs
+//!
+//! Following the rules of the ICFP 2004 contest, every program plays every other program on every
+//! world twice, once as red and once as black. A win is worth 2 points and a draw 1 point.
+
+use std::cmp::Ordering;
+
+use ant::AntColor;
+use instruction::Instruction;
+use simulator::{Outcome, Simulator};
+use util;
+use world::World;
+
+/// An ant program taking part in the tournament
+#[derive(Clone, Debug)]
+pub struct Entrant {
+    pub name: String,
+    pub instructions: Vec<Instruction>
+}
+
+/// A world on which the matches are played
+#[derive(Clone, Debug)]
+pub struct Arena {
+    pub name: String,
+    pub world: World
+}
+
+#[derive(Clone, Debug)]
+pub struct Tournament {
+    pub entrants: Vec<Entrant>,
+    pub arenas: Vec<Arena>,
+    pub rounds: u32,
+    pub seed: u32
+}
+
+/// A match between two entrants, identified by their indices in the tournament
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Pairing {
+    pub red: usize,
+    pub black: usize,
+    pub arena: usize
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MatchResult {
+    pub pairing: Pairing,
+    pub outcome: Outcome
+}
+
+/// The results of an entrant over the whole tournament
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Standing {
+    pub entrant: usize,
+    pub points: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// The food gathered over all matches, used to break ties
+    pub food: u32
+}
+
+impl Tournament {
+    /// All the matches of the tournament, in the order in which they are reported
+    pub fn pairings(&self) -> Vec<Pairing> {
+        let mut pairings = Vec::new();
+        for arena in 0..self.arenas.len() {
+            for first in 0..self.entrants.len() {
+                for second in first + 1..self.entrants.len() {
+                    pairings.push(Pairing { red: first, black: second, arena });
+                    pairings.push(Pairing { red: second, black: first, arena });
+                }
+            }
+        }
+
+        pairings
+    }
+
+    /// Plays a single match
+    pub fn play(&self, pairing: Pairing) -> Outcome {
+        let mut simulator = Simulator::new(self.arenas[pairing.arena].world.clone(),
+                                           self.entrants[pairing.red].instructions.clone(),
+                                           self.entrants[pairing.black].instructions.clone(),
+                                           self.rounds,
+                                           self.seed);
+        simulator.run()
+    }
+
+    /// Plays all the matches, distributed among the given amount of threads
+    ///
+    /// The results are in the same order as `pairings` and don't depend on the amount of threads
+    pub fn run(&self, threads: usize) -> Vec<MatchResult> {
+        // The threads need their own copy of the tournament
+        let tournament = self.clone();
+        let pairings = self.pairings();
+        let outcomes = util::parallel_map(pairings.clone(), threads, move |&pairing| tournament.play(pairing));
+        pairings.into_iter().zip(outcomes).map(|(pairing, outcome)| MatchResult { pairing, outcome }).collect()
+    }
+}
+
+/// Computes the league table, sorted from best to worst
+///
+/// Entrants with the same amount of points are ranked by the food they gathered
+pub fn standings(entrants: usize, results: &[MatchResult]) -> Vec<Standing> {
+    let mut standings: Vec<_> = (0..entrants).map(|entrant| Standing { entrant, ..Standing::default() }).collect();
+
+    for result in results {
+        let (red, black) = (result.pairing.red, result.pairing.black);
+        standings[red].food += result.outcome.red_score as u32;
+        standings[black].food += result.outcome.black_score as u32;
+
+        match result.outcome.winner() {
+            Some(AntColor::Red) => {
+                standings[red].wins += 1;
+                standings[black].losses += 1;
+            }
+            Some(AntColor::Black) => {
+                standings[black].wins += 1;
+                standings[red].losses += 1;
+            }
+            None => {
+                standings[red].draws += 1;
+                standings[black].draws += 1;
+            }
+        }
+    }
+
+    for standing in &mut standings {
+        standing.points = 2 * standing.wins + standing.draws;
+    }
+
+    standings.sort_by(|a, b| {
+        match b.points.cmp(&a.points) {
+            Ordering::Equal => b.food.cmp(&a.food).then(a.entrant.cmp(&b.entrant)),
+            ordering => ordering
+        }
+    });
+
+    standings
+}
@@ -10,6 +10,14 @@ impl BitField8 {
         BitField8 { bits: 0 }
     }
 
+    pub fn from_bits(bits: u8) -> BitField8 {
+        BitField8 { bits }
+    }
+
+    pub fn bits(self) -> u8 {
+        self.bits
+    }
+
     pub fn set_bit(&mut self, n: u8) {
         let mask = ONE << n;
         self.bits |= mask;
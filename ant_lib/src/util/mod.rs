@@ -1,5 +1,7 @@
 pub use self::bitfield::BitField8;
+pub use self::parallel::parallel_map;
 pub use self::rng::Rng;
 
 mod bitfield;
+mod parallel;
 mod rng;
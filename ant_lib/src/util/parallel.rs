@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Applies `f` to every item, distributing the work among the given amount of threads
+///
+/// The results are in the same order as the items and don't depend on the amount of threads
+pub fn parallel_map<T, R, F>(items: Vec<T>, threads: usize, f: F) -> Vec<R>
+where T: Send + Sync + 'static, R: Send + 'static, F: Fn(&T) -> R + Send + Sync + 'static {
+    let items = Arc::new(items);
+    let f = Arc::new(f);
+    let next = Arc::new(AtomicUsize::new(0));
+    let results = Arc::new(Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>()));
+
+    let workers: Vec<_> = (0..threads.max(1)).map(|_| {
+        let (items, f, next, results) = (items.clone(), f.clone(), next.clone(), results.clone());
+        thread::spawn(move || {
+            loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= items.len() {
+                    break;
+                }
+
+                let result = f(&items[i]);
+                results.lock().unwrap()[i] = Some(result);
+            }
+        })
+    }).collect();
+
+    for worker in workers {
+        worker.join().expect("A worker thread panicked");
+    }
+
+    let mut results = results.lock().unwrap();
+    results.drain(..).map(|result| result.unwrap()).collect()
+}
@@ -1,68 +1,272 @@
-use std::io::BufRead;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
 
-use ant::{Ant, AntColor, AntDirection};
+use ant::{Ant, AntColor, AntDirection, AntId};
+use generator::{self, WorldParams};
 use util::BitField8;
 
 // Note: the world is 0-indexed
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct World {
     pub cells: Vec<Cell>,
     pub width: usize,
     pub height: usize,
+    /// All ants, indexed by id, including the dead ones
+    pub ants: Vec<Ant>,
     red_anthill: Vec<usize>,
     black_anthill: Vec<usize>
 }
 
 impl World {
-    pub fn parse<'a, R>(mut reader: R) -> World
+    pub fn new(width: usize, height: usize, cells: Vec<Cell>) -> World {
+        World { width, height, cells, ants: Vec::new(), red_anthill: Vec::new(), black_anthill: Vec::new() }
+    }
+
+    /// Generates a random world
+    ///
+    /// Returns `None` if the elements of the world don't fit, which can only happen when the
+    /// default parameters are changed
+    pub fn generate(seed: u32, params: &WorldParams) -> Option<World> {
+        generator::generate(seed, params)
+    }
+
+    /// Parses a world in the standard text format and validates it
+    ///
+    /// All problems found in the input are collected and returned together
+    pub fn parse<R>(reader: R) -> Result<World, WorldError>
     where R: BufRead {
+        let mut violations = Vec::new();
+        let mut lines = reader.lines();
+
         let width;
         let height;
 
         {
-            let reader_mut = &mut reader;
-            let mut parse_dimension = || {
-                reader_mut.lines().next().unwrap().unwrap().trim().parse().expect("Invalid world dimension")
+            let lines_mut = &mut lines;
+            let mut parse_dimension = |line_number| {
+                let line = match lines_mut.next() {
+                    Some(Ok(line)) => line,
+                    Some(Err(e)) => return Err(Violation::Io(e.kind())),
+                    None => return Err(Violation::InvalidDimension { line: line_number, token: String::new() })
+                };
+
+                let token = line.trim();
+                token.parse().map_err(|_| Violation::InvalidDimension { line: line_number, token: token.to_string() })
             };
 
             // The first two lines are the X and Y dimensions
-            width = parse_dimension();
-            height = parse_dimension();
+            width = parse_dimension(1);
+            height = parse_dimension(2);
         }
 
-        // The rest of the lines are split into words, where each word is a cell
+        let (width, height) = match (width, height) {
+            (Ok(width), Ok(height)) => (width, height),
+            (width, height) => {
+                violations.extend(width.err());
+                violations.extend(height.err());
+                return Err(WorldError { violations });
+            }
+        };
+
+        // The rest of the lines are rows, split into words, where each word is a cell
         let mut cells = Vec::with_capacity(width * height);
-        for line in reader.lines() {
-            let line = line.unwrap();
-            let line = line.trim();
-            let words = line.split_whitespace();
-            cells.extend(words.map(Cell::parse));
+        let mut y = 0;
+        for line in lines {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    violations.push(Violation::Io(e.kind()));
+                    break;
+                }
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // Odd rows are shifted half a cell to the right
+            let indented = line.starts_with(char::is_whitespace);
+            if indented != (y % 2 == 1) {
+                violations.push(Violation::Indentation { y });
+            }
+
+            // Rows that are too long and too short may add up to the right amount of cells, which
+            // would misalign the world without this check
+            let found = line.split_whitespace().count();
+            if found != width {
+                violations.push(Violation::RowWidth { y, expected: width, found });
+            }
+
+            for (x, word) in line.split_whitespace().enumerate() {
+                match Cell::parse(word) {
+                    Some(cell) => cells.push(cell),
+                    None => {
+                        violations.push(Violation::InvalidCell { x, y, token: word.to_string() });
+                        cells.push(Cell::new());
+                    }
+                }
+            }
+
+            y += 1;
+        }
+
+        let world = World::new(width, height, cells);
+        if let Err(e) = world.validate() {
+            violations.extend(e.violations);
         }
 
-        World { width, height, cells, red_anthill: Vec::new(), black_anthill: Vec::new() }
+        if violations.is_empty() {
+            Ok(world)
+        } else {
+            Err(WorldError { violations })
+        }
     }
 
-    // Add the ants to the world and return a vector containing their indices in ascending order of id
-    pub fn populate(&mut self) -> Vec<usize> {
-        let mut ants = Vec::new();
+    /// Checks the invariants that the simulator relies upon
+    ///
+    /// The world must have exactly `width * height` cells, its perimeter must be rocky and the
+    /// amount of food in each cell must fit in a single digit
+    pub fn validate(&self) -> Result<(), WorldError> {
+        let expected = self.width * self.height;
+        if self.cells.len() != expected {
+            // The coordinates of the cells are meaningless, so there is nothing else to check
+            let violations = vec![Violation::CellCount { expected, found: self.cells.len() }];
+            return Err(WorldError { violations });
+        }
 
-        let mut ant_id = 0;
-        for (i, cell) in self.cells.iter_mut().enumerate() {
-            if let Some(color) = cell.anthill {
-                // Put ants on the anthills
-                cell.ant = Some(Ant::new(ant_id, color));
-                ants.push(i);
-                ant_id += 1;
-
-                // Save the coordinates of the anthill
-                match color {
-                    AntColor::Red => self.red_anthill.push(i),
-                    AntColor::Black => self.black_anthill.push(i)
+        let mut violations = Vec::new();
+        for (i, cell) in self.cells.iter().enumerate() {
+            let (x, y) = World::index_to_coords(self.width, i);
+            let on_border = x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1;
+            if on_border && !cell.is_rocky {
+                violations.push(Violation::NonRockyBorder { x, y });
+            }
+
+            if cell.food > 9 {
+                violations.push(Violation::FoodOutOfRange { x, y, food: cell.food });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(WorldError { violations })
+        }
+    }
+
+    /// Writes the world in the standard text format, which can be read back with `parse`
+    ///
+    /// Ants and markers are not part of the format, so they are ignored. Fails if a cell can't be
+    /// represented, e.g. because it has more than 9 units of food or it is an anthill with food.
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "{}", self.width)?;
+        writeln!(out, "{}", self.height)?;
+
+        for (y, row) in self.cells.chunks(self.width).enumerate() {
+            // Odd rows are shifted half a cell to the right
+            if y % 2 == 1 {
+                write!(out, " ")?;
+            }
+
+            for (x, cell) in row.iter().enumerate() {
+                if x > 0 {
+                    write!(out, " ")?;
                 }
+
+                let word = match (cell.is_rocky, cell.anthill, cell.food) {
+                    (true, None, 0) => '#',
+                    (false, Some(AntColor::Red), 0) => '+',
+                    (false, Some(AntColor::Black), 0) => '-',
+                    (false, None, 0) => '.',
+                    (false, None, food) if food <= 9 => (b'0' + food as u8) as char,
+                    _ => {
+                        let msg = format!("({}, {}): the cell can't be represented in the text format, \
+                                           consider writing a snapshot of the world instead", x, y);
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+                    }
+                };
+
+                write!(out, "{}", word)?;
+            }
+
+            writeln!(out, "")?;
+        }
+
+        Ok(())
+    }
+
+    /// A copy of the world without ants or markers, which keeps the current distribution of food
+    /// and can be used to start new games
+    ///
+    /// This turns a world in the middle of a game into one that can be written in the text format
+    /// and used to start new games. Food carried by ants is dropped on their cells and food stored in
+    /// the anthills is discarded, since it is already part of the score. Cells with more than 9
+    /// units of food spill the excess over the closest cells that have room for it.
+    pub fn to_starting_world(&self) -> World {
+        let mut cells: Vec<_> = self.cells.iter().map(|cell| {
+            let carried = cell.ant.map(|id| self.ants[id as usize].has_food as u16).unwrap_or(0);
+            let food = if cell.anthill.is_some() { 0 } else { cell.food + carried };
+            Cell { is_rocky: cell.is_rocky, anthill: cell.anthill, food, ..Cell::new() }
+        }).collect();
+
+        for i in 0..cells.len() {
+            if cells[i].food > 9 {
+                spill_food(self.width, &mut cells, i);
+            }
+        }
+
+        World::new(self.width, self.height, cells)
+    }
+
+    /// Puts an ant on each cell of the anthills, replacing the ants that were in the world
+    ///
+    /// Ants get their ids in the order of their cells
+    pub fn populate(&mut self) {
+        let ants = self.cells.iter().enumerate().filter_map(|(i, cell)| cell.anthill.map(|color| (i, color)))
+                                                .enumerate()
+                                                .map(|(id, (i, color))| Ant::new(id as AntId, color, i))
+                                                .collect();
+
+        self.place_ants(ants);
+    }
+
+    /// Replaces the ants of the world by the given ones, which must be indexed by id
+    ///
+    /// Each living ant is put in the cell given by its position
+    pub(crate) fn place_ants(&mut self, ants: Vec<Ant>) {
+        self.red_anthill.clear();
+        self.black_anthill.clear();
+
+        for (i, cell) in self.cells.iter_mut().enumerate() {
+            cell.ant = None;
+
+            // Save the coordinates of the anthill
+            match cell.anthill {
+                Some(AntColor::Red) => self.red_anthill.push(i),
+                Some(AntColor::Black) => self.black_anthill.push(i),
+                None => ()
             }
         }
 
-        ants
+        for ant in ants.iter().filter(|ant| ant.alive) {
+            self.cells[ant.position].ant = Some(ant.id);
+        }
+
+        self.ants = ants;
+    }
+
+    /// The ant in the cell at the given index, if any
+    pub fn ant_at(&self, i: usize) -> Option<&Ant> {
+        self.cells[i].ant.map(|id| &self.ants[id as usize])
+    }
+
+    pub fn ant_at_mut(&mut self, i: usize) -> Option<&mut Ant> {
+        match self.cells[i].ant {
+            Some(id) => Some(&mut self.ants[id as usize]),
+            None => None
+        }
     }
 
     pub fn count_red_food(&self) -> u16 {
@@ -74,19 +278,15 @@ impl World {
     }
 
     pub fn count_ants(&self) -> u16 {
-        self.cells.iter().filter(|cell| cell.ant.is_some()).count() as u16
+        self.ants.iter().filter(|ant| ant.alive).count() as u16
     }
 
     pub fn count_red_ants(&self) -> u16 {
-        self.cells.iter().filter_map(|cell| cell.ant.as_ref())
-                         .filter(|ant| ant.color == AntColor::Red)
-                         .count() as u16
+        self.ants.iter().filter(|ant| ant.alive && ant.color == AntColor::Red).count() as u16
     }
 
     pub fn count_black_ants(&self) -> u16 {
-        self.cells.iter().filter_map(|cell| cell.ant.as_ref())
-                         .filter(|ant| ant.color == AntColor::Black)
-                         .count() as u16
+        self.ants.iter().filter(|ant| ant.alive && ant.color == AntColor::Black).count() as u16
     }
 
     pub fn count_food(&self) -> u16 {
@@ -100,7 +300,7 @@ impl World {
     pub(crate) fn adjacent_enemies(&self, position: usize, friend_color: AntColor) -> usize {
         AntDirection::all()
                .map(|dir| World::adjacent_position(self.width, position, dir))
-               .filter_map(|i| self.cells[i].ant.as_ref())
+               .filter_map(|i| self.ant_at(i))
                .filter(|ant| ant.color != friend_color)
                .count()
     }
@@ -137,9 +337,43 @@ impl World {
     }
 }
 
+/// Moves the food above 9 units in the given cell to the closest cells with room for it
+fn spill_food(width: usize, cells: &mut [Cell], origin: usize) {
+    let mut excess = cells[origin].food - 9;
+    cells[origin].food = 9;
+
+    // Breadth-first search, which never crosses the rocky perimeter
+    let mut visited = vec![false; cells.len()];
+    let mut queue = VecDeque::new();
+    visited[origin] = true;
+    queue.push_back(origin);
+    while let Some(i) = queue.pop_front() {
+        {
+            let cell = &mut cells[i];
+            if cell.anthill.is_none() && cell.food < 9 {
+                let amount = excess.min(9 - cell.food);
+                cell.food += amount;
+                excess -= amount;
+                if excess == 0 {
+                    return;
+                }
+            }
+        }
+
+        for dir in AntDirection::all() {
+            let adjacent = World::adjacent_position(width, i, dir);
+            if !cells[adjacent].is_rocky && !visited[adjacent] {
+                visited[adjacent] = true;
+                queue.push_back(adjacent);
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Cell {
-    pub ant: Option<Ant>,
+    /// The id of the ant in this cell, which is an index in `World::ants`
+    pub ant: Option<AntId>,
     pub anthill: Option<AntColor>,
     pub is_rocky: bool,
     pub food: u16,
@@ -162,16 +396,20 @@ impl Cell {
         }
     }
 
-    fn parse(word: &str) -> Cell {
-        // The default cell
-        let mut cell = Cell {
+    /// An empty, clear cell
+    pub fn new() -> Cell {
+        Cell {
             ant: None,
             anthill: None,
             is_rocky: false,
             food: 0,
             markers_red: BitField8::new(),
             markers_black: BitField8::new()
-        };
+        }
+    }
+
+    fn parse(word: &str) -> Option<Cell> {
+        let mut cell = Cell::new();
 
         // Depending on the map, the default cell is modified
         match word {
@@ -179,9 +417,68 @@ impl Cell {
             "." => (),
             "+" => cell.anthill = Some(AntColor::Red),
             "-" => cell.anthill = Some(AntColor::Black),
-            num => cell.food = num.parse().expect("Invalid cell")
+            num => match num.parse() {
+                Ok(food) => cell.food = food,
+                Err(_) => return None
+            }
+        }
+
+        Some(cell)
+    }
+}
+
+/// The problems found while parsing or validating a world
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WorldError {
+    pub violations: Vec<Violation>
+}
+
+impl fmt::Display for WorldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                writeln!(f, "")?;
+            }
+
+            write!(f, "{}", violation)?;
         }
 
-        cell
+        Ok(())
+    }
+}
+
+impl Error for WorldError {
+    fn description(&self) -> &str {
+        "invalid world"
+    }
+}
+
+/// A broken invariant of the world format. Coordinates are 0-indexed
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Violation {
+    Io(io::ErrorKind),
+    InvalidDimension { line: usize, token: String },
+    InvalidCell { x: usize, y: usize, token: String },
+    CellCount { expected: usize, found: usize },
+    NonRockyBorder { x: usize, y: usize },
+    Indentation { y: usize },
+    RowWidth { y: usize, expected: usize, found: usize },
+    FoodOutOfRange { x: usize, y: usize, food: u16 }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Violation::*;
+        match *self {
+            Io(kind) => write!(f, "unable to read world: {:?}", kind),
+            InvalidDimension { line, ref token } => write!(f, "line {}: invalid dimension `{}`", line, token),
+            InvalidCell { x, y, ref token } => write!(f, "({}, {}): invalid cell `{}`", x, y, token),
+            CellCount { expected, found } => write!(f, "expected {} cells, found {}", expected, found),
+            NonRockyBorder { x, y } => write!(f, "({}, {}): border cell is not rocky", x, y),
+            Indentation { y } if y % 2 == 1 => write!(f, "row {}: odd rows must be indented", y),
+            Indentation { y } => write!(f, "row {}: even rows must not be indented", y),
+            RowWidth { y, expected, found } => write!(f, "row {}: expected {} cells, found {}", y, expected, found),
+            FoodOutOfRange { x, y, food } => write!(f, "({}, {}): food {} is out of range (0 to 9)", x, y, food)
+        }
     }
 }
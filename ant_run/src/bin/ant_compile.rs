@@ -0,0 +1,68 @@
+extern crate ant_lib;
+extern crate ant_run;
+extern crate structopt;
+#[macro_use] extern crate structopt_derive;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+use ant_lib::{compiler, optimizer, Instruction};
+use ant_run::{fatal_error, open_file_or_die};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "ant_compile", about = "Compiles an ant brain written in the structured language to instructions")]
+struct Options {
+    #[structopt(help = "The path of the source file")]
+    source: String,
+    #[structopt(long = "out", help = "The path of the instructions file (defaults to the standard output)")]
+    out: Option<String>,
+    #[structopt(long = "optimize", help = "Remove unreachable states and merge equivalent ones")]
+    optimize: bool,
+}
+
+fn write_instructions<W: Write>(mut out: W, instrs: &[Instruction]) -> io::Result<()> {
+    for instr in instrs {
+        writeln!(out, "{}", instr)?;
+    }
+
+    out.flush()
+}
+
+fn main() {
+    let options = Options::from_args();
+    let mut source = String::new();
+    if let Err(e) = open_file_or_die(&options.source).read_to_string(&mut source) {
+        fatal_error(&format!("unable to read file {}: {}", options.source, e));
+    }
+
+    let mut instrs = compiler::compile(&source).unwrap_or_else(|e| {
+        fatal_error(&format!("unable to compile {}:\n{}", options.source, e))
+    });
+
+    if options.optimize {
+        instrs = optimizer::optimize(&instrs).unwrap_or_else(|errors| {
+            let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+            fatal_error(&format!("unable to optimize {}:\n{}", options.source, errors.join("\n")))
+        });
+    }
+
+    let result = match options.out {
+        Some(ref path) => {
+            let file = File::create(path).unwrap_or_else(|e| {
+                fatal_error(&format!("unable to create file {}: {}", path, e))
+            });
+
+            write_instructions(BufWriter::new(file), &instrs)
+        }
+        None => {
+            let stdout = io::stdout();
+            let result = write_instructions(stdout.lock(), &instrs);
+            result
+        }
+    };
+
+    if let Err(e) = result {
+        fatal_error(&format!("unable to write instructions: {}", e));
+    }
+}
@@ -0,0 +1,52 @@
+extern crate ant_lib;
+extern crate ant_run;
+extern crate structopt;
+#[macro_use] extern crate structopt_derive;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use ant_lib::{analysis, Instruction};
+use ant_run::{fatal_error, open_file_or_die};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "ant_disasm", about = "Rewrites an ant brain with labels instead of numbered states")]
+struct Options {
+    #[structopt(help = "The path of the instructions file")]
+    instructions: String,
+    #[structopt(long = "out", help = "The path of the labelled instructions file (defaults to the standard output)")]
+    out: Option<String>,
+}
+
+fn main() {
+    let options = Options::from_args();
+    let path = &options.instructions;
+    let instrs = Instruction::parse(open_file_or_die(path)).unwrap_or_else(|e| {
+        fatal_error(&format!("invalid instructions in {}:\n{}", path, e))
+    });
+
+    // Broken brains are worth inspecting too, so their errors are only reported
+    for diagnostic in analysis::analyze(&instrs).iter().filter(|d| d.is_error()) {
+        eprintln!("{}: {}", path, diagnostic);
+    }
+
+    let result = match options.out {
+        Some(ref path) => {
+            let mut file = File::create(path).map(BufWriter::new).unwrap_or_else(|e| {
+                fatal_error(&format!("unable to create file {}: {}", path, e))
+            });
+
+            Instruction::disassemble(&instrs, &mut file).and_then(|_| file.flush())
+        }
+        None => {
+            let stdout = io::stdout();
+            let result = Instruction::disassemble(&instrs, stdout.lock());
+            result
+        }
+    };
+
+    if let Err(e) = result {
+        fatal_error(&format!("unable to write instructions: {}", e));
+    }
+}
@@ -0,0 +1,111 @@
+extern crate ant_lib;
+extern crate ant_run;
+extern crate num_cpus;
+extern crate structopt;
+#[macro_use] extern crate structopt_derive;
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use ant_lib::evolve::{Evolution, Params};
+use ant_lib::Instruction;
+use ant_run::{fatal_error, list_files, load_instructions, open_file_or_die};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "ant_evolve", about = "Evolves ant programs by playing them against a pool of opponents")]
+struct Options {
+    #[structopt(long = "opponents", help = "The directory containing the ant programs to play against")]
+    opponents: String,
+    #[structopt(long = "ancestors", help = "The directory containing the initial ant programs (defaults to the opponents)")]
+    ancestors: Option<String>,
+    #[structopt(long = "generations", help = "The amount of generations to evolve", default_value = "10")]
+    generations: u32,
+    #[structopt(long = "population", help = "The amount of programs in each generation", default_value = "20")]
+    population: usize,
+    #[structopt(long = "rounds", help = "The amount of rounds of each match", default_value = "10000")]
+    rounds: u32,
+    #[structopt(long = "seed", help = "The seed of the evolution", default_value = "12345")]
+    seed: u32,
+    #[structopt(long = "threads", help = "The amount of matches played in parallel (defaults to the amount of CPUs)")]
+    threads: Option<usize>,
+    #[structopt(long = "checkpoint", help = "A file to save the evolution after each generation, which is resumed if it exists")]
+    checkpoint: Option<String>,
+    #[structopt(long = "best", help = "The path where the best program of each generation is written", default_value = "best.ant")]
+    best: String,
+}
+
+fn load_brains(dir: &str) -> Vec<Vec<Instruction>> {
+    let brains: Vec<_> = list_files(dir).into_iter().map(|(_, path)| load_instructions(path)).collect();
+    if brains.is_empty() {
+        fatal_error(&format!("there are no ant programs in {}", dir));
+    }
+
+    brains
+}
+
+/// Writes to a temporary file first, so a crash never leaves a truncated file behind
+fn save<F: Fn(&mut BufWriter<File>) -> io::Result<()>>(path: &str, write: F) {
+    let temp = format!("{}.tmp", path);
+    let result = File::create(&temp).map(BufWriter::new).and_then(|mut file| {
+        write(&mut file)?;
+        file.flush()
+    }).and_then(|_| fs::rename(&temp, path));
+
+    if let Err(e) = result {
+        fatal_error(&format!("unable to write file {}: {}", path, e));
+    }
+}
+
+fn main() {
+    let options = Options::from_args();
+    if options.population < 2 {
+        fatal_error("the population needs at least 2 programs");
+    }
+
+    let params = Params { population: options.population, rounds: options.rounds, ..Params::default() };
+    if let Err(e) = params.validate() {
+        fatal_error(&e.to_string());
+    }
+
+    let opponents = load_brains(&options.opponents);
+
+    let mut evolution = match options.checkpoint {
+        Some(ref path) if Path::new(path).exists() => {
+            let evolution = Evolution::read_checkpoint(open_file_or_die(path), params, opponents).unwrap_or_else(|e| {
+                fatal_error(&format!("unable to resume from {}: {}", path, e))
+            });
+
+            println!("Resuming from generation {}", evolution.generation);
+            evolution
+        }
+        _ => {
+            let ancestors = load_brains(options.ancestors.as_ref().unwrap_or(&options.opponents));
+            Evolution::new(params, opponents, ancestors, options.seed)
+        }
+    };
+
+    let threads = options.threads.unwrap_or_else(num_cpus::get);
+    while evolution.generation < options.generations {
+        let generation = evolution.generation;
+        let ranked = evolution.step(threads).unwrap_or_else(|e| fatal_error(&e.to_string()));
+        println!("Generation {}: best fitness {}, median fitness {}, best size {} states",
+                 generation,
+                 ranked[0].fitness,
+                 ranked[ranked.len() / 2].fitness,
+                 ranked[0].instructions.len());
+
+        save(&options.best, |file| {
+            for instr in &ranked[0].instructions {
+                writeln!(file, "{}", instr)?;
+            }
+
+            Ok(())
+        });
+
+        if let Some(ref path) = options.checkpoint {
+            save(path, |file| evolution.write_checkpoint(file));
+        }
+    }
+}
@@ -0,0 +1,93 @@
+extern crate ant_lib;
+extern crate ant_run;
+extern crate num_cpus;
+extern crate structopt;
+#[macro_use] extern crate structopt_derive;
+
+use ant_lib::tournament::{self, Arena, Entrant, MatchResult, Standing, Tournament};
+use ant_run::{fatal_error, list_files, load_instructions, load_world, winner_name};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "ant_tournament", about = "Plays a round-robin tournament between all ant programs in a directory")]
+struct Options {
+    #[structopt(long = "brains", help = "The directory containing the ant programs")]
+    brains: String,
+    #[structopt(long = "worlds", help = "The directory containing the worlds")]
+    worlds: String,
+    #[structopt(long = "rounds", help = "The amount of rounds of each match", default_value = "100000")]
+    rounds: u32,
+    #[structopt(long = "seed", help = "The seed that will be used by the RNG in every match", default_value = "12345")]
+    seed: u32,
+    #[structopt(long = "threads", help = "The amount of matches played in parallel (defaults to the amount of CPUs)")]
+    threads: Option<usize>,
+}
+
+fn main() {
+    let options = Options::from_args();
+
+    let entrants: Vec<_> = list_files(&options.brains).into_iter().map(|(name, path)| {
+        Entrant { name, instructions: load_instructions(path) }
+    }).collect();
+
+    let arenas: Vec<_> = list_files(&options.worlds).into_iter().map(|(name, path)| {
+        Arena { name, world: load_world(path) }
+    }).collect();
+
+    if entrants.len() < 2 {
+        fatal_error("a tournament needs at least two ant programs");
+    }
+
+    if arenas.is_empty() {
+        fatal_error("a tournament needs at least one world");
+    }
+
+    let tournament = Tournament { entrants, arenas, rounds: options.rounds, seed: options.seed };
+    let threads = options.threads.unwrap_or_else(num_cpus::get);
+    println!("Playing {} matches on {} threads", tournament.pairings().len(), threads);
+
+    let results = tournament.run(threads);
+    print_matches(&tournament, &results);
+    println!("");
+    print_table(&tournament, &tournament::standings(tournament.entrants.len(), &results));
+}
+
+fn print_matches(tournament: &Tournament, results: &[MatchResult]) {
+    let name_width = tournament.entrants.iter().map(|e| e.name.len()).max().unwrap_or(0);
+    let arena_width = tournament.arenas.iter().map(|a| a.name.len()).max().unwrap_or(0);
+
+    println!("Matches");
+    for result in results {
+        let (pairing, outcome) = (result.pairing, result.outcome);
+        println!("  {:aw$}  {:nw$} (red) {:4} - {:<4} {:nw$} (black)  alive: {:3} - {:<3}  food left: {:4}  winner: {}",
+                 tournament.arenas[pairing.arena].name,
+                 tournament.entrants[pairing.red].name,
+                 outcome.red_score,
+                 outcome.black_score,
+                 tournament.entrants[pairing.black].name,
+                 outcome.red_alive,
+                 outcome.black_alive,
+                 outcome.food_left,
+                 winner_name(&outcome).unwrap_or("none"),
+                 aw = arena_width,
+                 nw = name_width);
+    }
+}
+
+fn print_table(tournament: &Tournament, standings: &[Standing]) {
+    let name_width = tournament.entrants.iter().map(|e| e.name.len()).max().unwrap_or(0).max(4);
+
+    println!("League table");
+    println!("  {:>3}  {:nw$}  {:>6}  {:>4}  {:>4}  {:>4}  {:>6}", "#", "Name", "Points", "Won", "Drew", "Lost", "Food", nw = name_width);
+    for (i, standing) in standings.iter().enumerate() {
+        println!("  {:>3}  {:nw$}  {:>6}  {:>4}  {:>4}  {:>4}  {:>6}",
+                 i + 1,
+                 tournament.entrants[standing.entrant].name,
+                 standing.points,
+                 standing.wins,
+                 standing.draws,
+                 standing.losses,
+                 standing.food,
+                 nw = name_width);
+    }
+}
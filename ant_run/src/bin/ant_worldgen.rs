@@ -0,0 +1,46 @@
+extern crate ant_lib;
+extern crate ant_run;
+extern crate structopt;
+#[macro_use] extern crate structopt_derive;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use ant_lib::{World, WorldParams};
+use ant_run::fatal_error;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "ant_worldgen", about = "Generates random worlds following the rules of the ICFP 2004 tournament")]
+struct Options {
+    #[structopt(long = "seed", help = "The seed of the generated world", default_value = "12345")]
+    seed: u32,
+    #[structopt(long = "out", help = "The path of the world file (defaults to the standard output)")]
+    out: Option<String>,
+}
+
+fn main() {
+    let options = Options::from_args();
+    let world = World::generate(options.seed, &WorldParams::default()).unwrap_or_else(|| {
+        fatal_error("unable to fit all elements in the world")
+    });
+
+    let result = match options.out {
+        Some(ref path) => {
+            let mut file = File::create(path).map(BufWriter::new).unwrap_or_else(|e| {
+                fatal_error(&format!("unable to create file {}: {}", path, e))
+            });
+
+            world.write_to(&mut file).and_then(|_| file.flush())
+        }
+        None => {
+            let stdout = io::stdout();
+            let result = world.write_to(stdout.lock());
+            result
+        }
+    };
+
+    if let Err(e) = result {
+        fatal_error(&format!("unable to write world: {}", e));
+    }
+}
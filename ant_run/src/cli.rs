@@ -0,0 +1,24 @@
+#[derive(StructOpt, Debug)]
+#[structopt(name = "ant_run", about = "Plays a match between two ant programs without opening a window")]
+pub struct Options {
+    #[structopt(long = "world", help = "The path to the world file")]
+    pub world: Option<String>,
+    #[structopt(long = "red", help = "The path to the instructions of the red team")]
+    pub red: Option<String>,
+    #[structopt(long = "black", help = "The path to the instructions of the black team")]
+    pub black: Option<String>,
+    #[structopt(long = "rounds", help = "The amount of rounds to be executed", default_value = "100000")]
+    pub rounds: u32,
+    #[structopt(long = "seed", help = "The seed that will be used by the RNG", default_value = "12345")]
+    pub seed: u32,
+    #[structopt(long = "dump", help = "Write the state of the world after each round to this file, in the ICFP 2004 dump format")]
+    pub dump: Option<String>,
+    #[structopt(long = "save-world", help = "Write the world at the end of the match to this file, as a world to start new games from")]
+    pub save_world: Option<String>,
+    #[structopt(long = "snapshot", help = "Resume the game saved in this snapshot file, ignoring the world, the brains, the rounds and the seed")]
+    pub snapshot: Option<String>,
+    #[structopt(long = "save-snapshot", help = "Write a snapshot of the game at the end of the match to this file, so it can be resumed later")]
+    pub save_snapshot: Option<String>,
+    #[structopt(long = "json", help = "Print the outcome as JSON instead of plain text")]
+    pub json: bool,
+}
@@ -0,0 +1,76 @@
+//! Helpers shared by the command line tools of this crate
+
+extern crate ant_lib;
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use ant_lib::{analysis, AntColor, Instruction, Outcome, Snapshot, World};
+
+pub fn fatal_error(msg: &str) -> ! {
+    eprintln!("Fatal error: {}", msg);
+    process::exit(1);
+}
+
+pub fn open_file_or_die<P: AsRef<Path>>(path: P) -> BufReader<File> {
+    let path = path.as_ref();
+    File::open(path).map(|file| BufReader::new(file))
+                    .unwrap_or_else(|_| fatal_error(&format!("unable to open file: {}", path.display())))
+}
+
+/// Loads an ant program, exiting if it is invalid or would crash the simulator
+pub fn load_instructions<P: AsRef<Path>>(path: P) -> Vec<Instruction> {
+    let path = path.as_ref();
+    let instrs = Instruction::parse(open_file_or_die(path)).unwrap_or_else(|e| {
+        fatal_error(&format!("invalid instructions in {}:\n{}", path.display(), e))
+    });
+
+    let diagnostics = analysis::analyze(&instrs);
+    for diagnostic in diagnostics.iter().filter(|d| d.is_error()) {
+        eprintln!("{}: {}", path.display(), diagnostic);
+    }
+
+    if diagnostics.iter().any(|d| d.is_error()) {
+        fatal_error(&format!("the instructions in {} would crash the simulator", path.display()));
+    }
+
+    instrs
+}
+
+/// Loads a world, exiting if it is invalid
+pub fn load_world<P: AsRef<Path>>(path: P) -> World {
+    let path = path.as_ref();
+    World::parse(open_file_or_die(path)).unwrap_or_else(|e| {
+        fatal_error(&format!("invalid world in {}:\n{}", path.display(), e))
+    })
+}
+
+/// Loads a snapshot of a game, exiting if it is invalid
+pub fn load_snapshot<P: AsRef<Path>>(path: P) -> Snapshot {
+    let path = path.as_ref();
+    Snapshot::parse(open_file_or_die(path)).unwrap_or_else(|e| {
+        fatal_error(&format!("unable to load snapshot from {}: {}", path.display(), e))
+    })
+}
+
+/// The files in a directory, sorted by name, together with their names without extension
+pub fn list_files<P: AsRef<Path>>(dir: P) -> Vec<(String, PathBuf)> {
+    let dir = dir.as_ref();
+    let entries = fs::read_dir(dir).unwrap_or_else(|e| {
+        fatal_error(&format!("unable to read directory {}: {}", dir.display(), e))
+    });
+
+    let mut files: Vec<_> = entries.filter_map(|entry| entry.ok())
+                                   .map(|entry| entry.path())
+                                   .filter(|path| path.is_file())
+                                   .map(|path| (path.file_stem().unwrap().to_string_lossy().into_owned(), path))
+                                   .collect();
+    files.sort();
+    files
+}
+
+pub fn winner_name(outcome: &Outcome) -> Option<&'static str> {
+    outcome.winner().map(AntColor::name)
+}
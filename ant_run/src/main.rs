@@ -0,0 +1,99 @@
+extern crate ant_lib;
+extern crate ant_run;
+extern crate structopt;
+#[macro_use] extern crate structopt_derive;
+
+mod cli;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use ant_lib::{dump, test_data, Instruction, Outcome, Simulator, World};
+use ant_run::{fatal_error, load_instructions, load_snapshot, load_world, winner_name};
+use structopt::StructOpt;
+
+use cli::Options;
+
+fn main() {
+    let options = Options::from_args();
+    let mut simulator = match options.snapshot {
+        Some(ref path) => Simulator::restore(load_snapshot(path)),
+        None => {
+            let (red, black, world) = load(&options);
+            Simulator::new(world, red, black, options.rounds, options.seed)
+        }
+    };
+
+    let outcome = match options.dump {
+        Some(ref path) => {
+            let mut file = File::create(path).map(BufWriter::new).unwrap_or_else(|e| {
+                fatal_error(&format!("unable to create file {}: {}", path, e))
+            });
+
+            dump::dump_game(&mut file, &mut simulator).and_then(|_| file.flush()).unwrap_or_else(|e| {
+                fatal_error(&format!("unable to write dump to {}: {}", path, e))
+            });
+
+            simulator.partial_outcome()
+        }
+        None => simulator.run()
+    };
+
+    if let Some(ref path) = options.save_world {
+        let result = File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            simulator.world.to_starting_world().write_to(&mut writer)?;
+            writer.flush()
+        });
+
+        if let Err(e) = result {
+            fatal_error(&format!("unable to write world to {}: {}", path, e));
+        }
+    }
+
+    if let Some(ref path) = options.save_snapshot {
+        let result = File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            simulator.snapshot().write_to(&mut writer)?;
+            writer.flush()
+        });
+
+        if let Err(e) = result {
+            fatal_error(&format!("unable to write snapshot to {}: {}", path, e));
+        }
+    }
+
+    if options.json {
+        println!("{}", outcome_json(&outcome));
+    } else {
+        print_outcome(&outcome);
+    }
+}
+
+fn print_outcome(outcome: &Outcome) {
+    println!("Rounds:    {}", outcome.round);
+    println!("Red:       score {}, {} ants alive", outcome.red_score, outcome.red_alive);
+    println!("Black:     score {}, {} ants alive", outcome.black_score, outcome.black_alive);
+    println!("Food left: {}", outcome.food_left);
+    println!("Winner:    {}", winner_name(outcome).unwrap_or("none"));
+}
+
+fn outcome_json(outcome: &Outcome) -> String {
+    let winner = winner_name(outcome).map(|w| format!("\"{}\"", w)).unwrap_or_else(|| "null".to_string());
+    format!("{{\"round\":{},\"red_score\":{},\"red_alive\":{},\"black_score\":{},\"black_alive\":{},\"food_left\":{},\"winner\":{}}}",
+            outcome.round,
+            outcome.red_score,
+            outcome.red_alive,
+            outcome.black_score,
+            outcome.black_alive,
+            outcome.food_left,
+            winner)
+}
+
+fn load(options: &Options) -> (Vec<Instruction>, Vec<Instruction>, World) {
+    let red = options.red.as_ref().map(load_instructions).unwrap_or_else(test_data::ant1);
+    let black = options.black.as_ref().map(load_instructions).unwrap_or_else(test_data::ant1);
+    let world = options.world.as_ref().map(load_world).unwrap_or_else(test_data::sample0);
+
+    (red, black, world)
+}
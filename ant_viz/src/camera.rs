@@ -1,10 +1,22 @@
+use std::ops::Range;
+
 use ant_lib::World;
-use view::{CELL_WIDTH, ROW_HEIGHT};
+use piston_window::Transformed;
+use piston_window::math::Matrix2d;
+use view::{self, CELL_WIDTH, ROW_HEIGHT};
+
+const MIN_ZOOM: f64 = 0.05;
+const MAX_ZOOM: f64 = 8.0;
 
+/// The part of the world that is shown on the screen
+///
+/// The position of the camera is the world point (in unzoomed pixels) shown at the top-left corner
+/// of the screen, so a world point `p` is drawn at `(p - position) * zoom`.
 #[derive(Clone, Copy, Default)]
 pub struct Camera {
     pub x: f64,
     pub y: f64,
+    pub zoom: f64,
     pub world_width: f64,
     pub world_height: f64,
     pub scr_width: f64,
@@ -13,22 +25,26 @@ pub struct Camera {
 
 impl Camera {
     pub fn new(scr_width: f64, scr_height: f64, world: &World) -> Camera {
+        let (world_width, world_height) = view::world_size(world);
         Camera {
             scr_width,
             scr_height,
-            world_width: CELL_WIDTH * world.width as f64 + CELL_WIDTH / 2.0,
-            world_height: ROW_HEIGHT * world.height as f64 + ROW_HEIGHT / 3.0,
+            zoom: 1.0,
+            world_width,
+            world_height,
             ..Camera::default()
         }
     }
 
+    /// Moves the camera by the given amount of screen pixels
     pub fn move_x(&mut self, units: f64) {
-        self.x += units;
+        self.x += units / self.zoom;
         self.adjust_x();
     }
 
+    /// Moves the camera by the given amount of screen pixels
     pub fn move_y(&mut self, units: f64) {
-        self.y += units;
+        self.y += units / self.zoom;
         self.adjust_y();
     }
 
@@ -39,25 +55,77 @@ impl Camera {
         self.adjust_y();
     }
 
+    /// Multiplies the zoom by the given factor, keeping the given screen point over the same
+    /// world point
+    pub fn zoom_at(&mut self, factor: f64, scr_x: f64, scr_y: f64) {
+        let (world_x, world_y) = self.to_world(scr_x, scr_y);
+        self.zoom = (self.zoom * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+        self.x = world_x - scr_x / self.zoom;
+        self.y = world_y - scr_y / self.zoom;
+        self.adjust_x();
+        self.adjust_y();
+    }
+
+    /// Multiplies the zoom by the given factor, keeping the center of the screen in place
+    pub fn zoom_by(&mut self, factor: f64) {
+        let (center_x, center_y) = (self.scr_width / 2.0, self.scr_height / 2.0);
+        self.zoom_at(factor, center_x, center_y);
+    }
+
+    /// Zooms so the whole world fits in the screen
+    pub fn fit(&mut self) {
+        let zoom = (self.scr_width / self.world_width).min(self.scr_height / self.world_height);
+        self.zoom = zoom.max(MIN_ZOOM).min(MAX_ZOOM);
+        self.adjust_x();
+        self.adjust_y();
+    }
+
+    /// The world point at the given screen point
+    pub fn to_world(&self, scr_x: f64, scr_y: f64) -> (f64, f64) {
+        (self.x + scr_x / self.zoom, self.y + scr_y / self.zoom)
+    }
+
+    /// Turns a transform in screen coordinates into one in world coordinates
+    pub fn transform(&self, screen: Matrix2d) -> Matrix2d {
+        screen.zoom(self.zoom).trans(-self.x, -self.y)
+    }
+
+    /// The columns and rows of the cells that are at least partially on the screen
+    pub fn visible_cells(&self, world: &World) -> (Range<usize>, Range<usize>) {
+        let (left, top) = self.to_world(0.0, 0.0);
+        let (right, bottom) = self.to_world(self.scr_width, self.scr_height);
+
+        // Odd rows are shifted and rows overlap, so one more cell is taken on each side
+        let range = |from: f64, to: f64, size: f64, count: usize| {
+            let start = ((from / size).floor() - 1.0).max(0.0) as usize;
+            let end = ((to / size).ceil() + 1.0).max(0.0) as usize;
+            start.min(count)..end.min(count)
+        };
+
+        (range(left, right, CELL_WIDTH, world.width), range(top, bottom, ROW_HEIGHT, world.height))
+    }
+
     fn adjust_x(&mut self) {
-        if self.scr_width < self.world_width {
-            let right_limit = self.world_width - self.scr_width - 1.0;
+        let visible_width = self.scr_width / self.zoom;
+        if visible_width < self.world_width {
+            let right_limit = self.world_width - visible_width;
             self.x = self.x.max(0.0);
             self.x = self.x.min(right_limit);
         } else {
-            // If the screen is bigger than the world, we should fix the camera to 0.0
-            self.x = 0.0;
+            // If the screen is bigger than the world, we should center the world
+            self.x = (self.world_width - visible_width) / 2.0;
         }
     }
 
     fn adjust_y(&mut self) {
-        if self.scr_height < self.world_height {
-            let bottom_limit = self.world_height - self.scr_height - 1.0;
+        let visible_height = self.scr_height / self.zoom;
+        if visible_height < self.world_height {
+            let bottom_limit = self.world_height - visible_height;
             self.y = self.y.max(0.0);
             self.y = self.y.min(bottom_limit);
         } else {
-            // If the screen is bigger than the world, we should fix the camera to 0.0
-            self.y = 0.0;
+            // If the screen is bigger than the world, we should center the world
+            self.y = (self.world_height - visible_height) / 2.0;
         }
     }
 }
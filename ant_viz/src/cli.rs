@@ -11,6 +11,22 @@ pub struct Options {
     pub rounds: u32,
     #[structopt(long = "seed", help = "The seed that will be used by the RNG", default_value = "12345")]
     pub seed: u32,
+    #[structopt(long = "snapshot", help = "Resume the game saved in this snapshot file, ignoring the world, the brains, the rounds and the seed")]
+    pub snapshot: Option<String>,
+    #[structopt(long = "keyframe-interval", help = "The amount of rounds between the states of the game kept to go back in time", default_value = "100")]
+    pub keyframe_interval: u32,
+    #[structopt(long = "keyframes", help = "The maximum amount of states of the game kept to go back in time", default_value = "200")]
+    pub keyframes: usize,
     #[structopt(long = "rps", help = "The amount of rounds that will be executed per second", default_value = "200")]
     pub rounds_per_second: u32,
+    #[structopt(long = "png-dir", help = "Play the game without a window, writing a PNG image every few rounds into this directory")]
+    pub png_dir: Option<String>,
+    #[structopt(long = "gif", help = "Play the game without a window, writing an animated GIF of it to this file")]
+    pub gif: Option<String>,
+    #[structopt(long = "frame-interval", help = "The amount of rounds between the images written with --png-dir and --gif", default_value = "100")]
+    pub frame_interval: u32,
+    #[structopt(long = "frame-scale", help = "The size of the images written with --png-dir and --gif, relative to the window at zoom 1", default_value = "0.5")]
+    pub frame_scale: f64,
+    #[structopt(long = "frame-markers", help = "The markers shown in the images written with --png-dir and --gif: none, red, black or both", default_value = "none")]
+    pub frame_markers: String,
 }
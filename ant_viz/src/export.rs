@@ -0,0 +1,456 @@
+//! Rendering of games to image files, without opening a window
+//!
+//! The images are drawn by a small software rasterizer from the same `Scene` as the window, so
+//! they show the cells, ants, food and markers exactly like `View` does, minus the antialiasing.
+
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use ant_lib::{Simulator, World};
+use gif::{self, SetParameter};
+use image;
+use piston_window::math::Matrix2d;
+
+use view::{self, Markers, Scene};
+
+/// The time each frame of a GIF is shown, in hundredths of a second
+const GIF_FRAME_DELAY: u16 = 10;
+
+/// The size of the pixels of the digits, in world pixels
+const DIGIT_PIXEL: f64 = 1.4;
+
+/// The digits 0 to 9 as 3x5 bitmaps, one row per byte with the leftmost pixel in the highest bit
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// What to write and how the images look
+pub struct Export {
+    /// The directory where a PNG image is written for every frame
+    pub png_dir: Option<PathBuf>,
+    /// The file where an animated GIF of all the frames is written
+    pub gif: Option<PathBuf>,
+    /// The amount of rounds between frames
+    pub interval: u32,
+    /// The size of the images relative to the window at zoom 1
+    pub scale: f64,
+    pub show_marks: Markers
+}
+
+/// Plays the game until its last round, writing a frame every `interval` rounds
+///
+/// The first frame shows the current round and the last one the end of the game. Returns the
+/// amount of frames written.
+pub fn run(simulator: &mut Simulator, export: &Export) -> io::Result<u32> {
+    if let Some(ref dir) = export.png_dir {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut encoder = match export.gif {
+        Some(ref path) => {
+            let (width, height) = image_size(&simulator.world, export.scale);
+            if width > u16::max_value() as u32 || height > u16::max_value() as u32 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "the image is too big for a GIF"));
+            }
+
+            let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), width as u16, height as u16, &[])?;
+            encoder.set(gif::Repeat::Infinite)?;
+            Some(encoder)
+        }
+        None => None
+    };
+
+    let mut frames = 0;
+    loop {
+        let canvas = render(&simulator.world, export.show_marks, export.scale);
+        if let Some(ref dir) = export.png_dir {
+            let path = dir.join(format!("round-{:06}.png", simulator.round));
+            image::save_buffer(&path, &canvas.pixels, canvas.width, canvas.height, image::ColorType::RGBA(8))?;
+        }
+
+        if let Some(ref mut encoder) = encoder {
+            let mut frame = canvas.gif_frame();
+            frame.delay = GIF_FRAME_DELAY;
+            encoder.write_frame(&frame)?;
+        }
+
+        frames += 1;
+        if simulator.round >= simulator.max_rounds {
+            return Ok(frames);
+        }
+
+        let rounds = cmp::min(export.interval.max(1), simulator.max_rounds - simulator.round);
+        for _ in 0..rounds {
+            simulator.one_round();
+        }
+    }
+}
+
+/// Draws the whole world, with each pixel of the world at zoom 1 taking `scale` pixels of the image
+pub fn render(world: &World, show_marks: Markers, scale: f64) -> Canvas {
+    let (width, height) = image_size(world, scale);
+    let mut canvas = Canvas::new(width, height);
+    let (canvas_width, canvas_height) = (canvas.width as f64, canvas.height as f64);
+    canvas.fill_rect(view::WORLD_COLOR, 0.0, 0.0, canvas_width, canvas_height);
+
+    let cells: Vec<_> = (0..world.cells.len()).collect();
+    let trans = [[scale, 0.0, 0.0], [0.0, scale, 0.0]];
+    let scene = Scene::new(world, &cells, show_marks, &[true; 6], trans);
+    for layer in &scene.cells {
+        for &(color, ref transforms) in &layer.batches.batches {
+            for &m in transforms {
+                canvas.fill_polygon(color, &layer.polygon, m);
+            }
+        }
+    }
+
+    if view::food_font_size(scale).is_some() {
+        for &(food, label_trans) in &scene.food_labels {
+            canvas.draw_number(view::FOOD_TEXT_COLOR, food, label_trans);
+        }
+    }
+
+    for layer in &scene.ants {
+        for &(color, ref transforms) in &layer.batches.batches {
+            for &m in transforms {
+                canvas.fill_polygon(color, &layer.polygon, m);
+            }
+        }
+    }
+
+    canvas
+}
+
+fn image_size(world: &World, scale: f64) -> (u32, u32) {
+    let (width, height) = view::world_size(world);
+    ((width * scale).ceil() as u32, (height * scale).ceil() as u32)
+}
+
+/// An opaque RGBA image, on which shapes are drawn by blending their colors with the pixels below
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>
+}
+
+impl Canvas {
+    /// Creates a black canvas
+    pub fn new(width: u32, height: u32) -> Canvas {
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        for pixel in pixels.chunks_mut(4) {
+            pixel[3] = 255;
+        }
+
+        Canvas { width, height, pixels }
+    }
+
+    /// Fills the pixels whose centers are inside of the given convex polygon
+    pub fn fill_polygon(&mut self, color: [f32; 4], polygon: &[[f64; 2]], m: Matrix2d) {
+        let points: Vec<_> = polygon.iter().map(|p| transform(m, p)).collect();
+        let min_x = points.iter().map(|p| p[0]).fold(::std::f64::INFINITY, f64::min);
+        let max_x = points.iter().map(|p| p[0]).fold(::std::f64::NEG_INFINITY, f64::max);
+        let min_y = points.iter().map(|p| p[1]).fold(::std::f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p[1]).fold(::std::f64::NEG_INFINITY, f64::max);
+
+        let (columns, rows) = (pixel_range(min_x, max_x, self.width), pixel_range(min_y, max_y, self.height));
+        for y in rows {
+            for x in columns.clone() {
+                if in_polygon(&points, x as f64 + 0.5, y as f64 + 0.5) {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Fills the pixels whose centers are inside of the given rectangle, in image coordinates
+    pub fn fill_rect(&mut self, color: [f32; 4], x: f64, y: f64, width: f64, height: f64) {
+        let (columns, rows) = (pixel_range(x, x + width, self.width), pixel_range(y, y + height, self.height));
+        for y in rows {
+            for x in columns.clone() {
+                self.blend(x, y, color);
+            }
+        }
+    }
+
+    /// Draws a number with its bottom-left corner at the origin of the given transform
+    ///
+    /// The digits are aligned to the pixels of the image, so they stay readable when scaled down.
+    fn draw_number(&mut self, color: [f32; 4], number: u16, m: Matrix2d) {
+        let size = (DIGIT_PIXEL * m[0][0]).round().max(1.0);
+        let origin = transform(m, &[0.0, 0.0]);
+        let (left, top) = (origin[0].round(), origin[1].round() - 5.0 * size);
+        for (i, digit) in number.to_string().bytes().map(|b| (b - b'0') as usize).enumerate() {
+            for (row, bits) in DIGITS[digit].iter().enumerate() {
+                for column in (0..3).filter(|&column| bits & (0b100 >> column) != 0) {
+                    let x = left + (i * 4 + column) as f64 * size;
+                    self.fill_rect(color, x, top + row as f64 * size, size, size);
+                }
+            }
+        }
+    }
+
+    fn blend(&mut self, x: u32, y: u32, color: [f32; 4]) {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = color[3];
+        for (channel, &value) in self.pixels[i..i + 3].iter_mut().zip(&color[..3]) {
+            let blended = value * alpha + *channel as f32 / 255.0 * (1.0 - alpha);
+            *channel = (blended * 255.0).round() as u8;
+        }
+    }
+
+    /// The image as a GIF frame, with a palette of its own
+    fn gif_frame(&self) -> gif::Frame<'static> {
+        let mut palette = Vec::new();
+        let mut indices = HashMap::new();
+        let mut buffer = Vec::with_capacity(self.pixels.len() / 4);
+        for pixel in self.pixels.chunks(4) {
+            let next = indices.len();
+            let index = *indices.entry([pixel[0], pixel[1], pixel[2]]).or_insert(next);
+            if index > 255 {
+                // There are too many colors to index them, so let the encoder quantize the image
+                let mut pixels = self.pixels.clone();
+                return gif::Frame::from_rgba(self.width as u16, self.height as u16, &mut pixels);
+            }
+
+            if index == next {
+                palette.extend_from_slice(&pixel[..3]);
+            }
+
+            buffer.push(index as u8);
+        }
+
+        let mut frame = gif::Frame::default();
+        frame.width = self.width as u16;
+        frame.height = self.height as u16;
+        frame.palette = Some(palette);
+        frame.buffer = Cow::Owned(buffer);
+        frame
+    }
+}
+
+/// The pixels whose centers are between `from` and `to`, out of `count`
+fn pixel_range(from: f64, to: f64, count: u32) -> Range<u32> {
+    let start = (from - 0.5).ceil().max(0.0).min(count as f64) as u32;
+    let end = ((to - 0.5).floor() + 1.0).max(0.0).min(count as f64) as u32;
+    start..cmp::max(start, end)
+}
+
+fn transform(m: Matrix2d, p: &[f64; 2]) -> [f64; 2] {
+    [m[0][0] * p[0] + m[0][1] * p[1] + m[0][2], m[1][0] * p[0] + m[1][1] * p[1] + m[1][2]]
+}
+
+/// Whether a point is inside of a convex polygon, which is the case when it is on the same side
+/// of all of its edges
+fn in_polygon(points: &[[f64; 2]], x: f64, y: f64) -> bool {
+    let mut side = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let cross = (b[0] - a[0]) * (y - a[1]) - (b[1] - a[1]) * (x - a[0]);
+        if cross * side < 0.0 {
+            return false;
+        }
+
+        if cross != 0.0 {
+            side = cross;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use ant_lib::World;
+    use gif::{self, SetParameter};
+
+    use view::{self, Markers};
+    use super::{in_polygon, pixel_range, render, Canvas};
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+    fn pixel(canvas: &Canvas, x: u32, y: u32) -> [u8; 3] {
+        let i = (y as usize * canvas.width as usize + x as usize) * 4;
+        [canvas.pixels[i], canvas.pixels[i + 1], canvas.pixels[i + 2]]
+    }
+
+    /// The rows of the canvas, with `#` for the pixels of the given color and `.` for the rest
+    fn rows(canvas: &Canvas, color: [u8; 3]) -> Vec<String> {
+        (0..canvas.height).map(|y| {
+            (0..canvas.width).map(|x| if pixel(canvas, x, y) == color { '#' } else { '.' }).collect()
+        }).collect()
+    }
+
+    /// Writes a frame to a GIF and reads it back as RGBA pixels
+    fn gif_round_trip(frame: &gif::Frame) -> Vec<u8> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut data, frame.width, frame.height, &[]).unwrap();
+            encoder.write_frame(frame).unwrap();
+        }
+
+        let mut decoder = gif::Decoder::new(&data[..]);
+        decoder.set(gif::ColorOutput::RGBA);
+        let mut reader = decoder.read_info().unwrap();
+        let frame = reader.read_next_frame().unwrap().unwrap();
+        frame.buffer.to_vec()
+    }
+
+    #[test]
+    fn test_pixel_range() {
+        // The pixels whose centers are covered
+        assert_eq!(pixel_range(0.0, 2.0, 10), 0..2);
+        assert_eq!(pixel_range(0.4, 2.6, 10), 0..3);
+        assert_eq!(pixel_range(0.6, 2.4, 10), 1..2);
+
+        // Nothing when no center is covered
+        assert_eq!(pixel_range(0.6, 1.4, 10), 1..1);
+
+        // Clamped to the image
+        assert_eq!(pixel_range(-5.0, 3.0, 10), 0..3);
+        assert_eq!(pixel_range(8.0, 20.0, 10), 8..10);
+        assert!(pixel_range(12.0, 20.0, 10).next().is_none());
+        assert!(pixel_range(-20.0, -12.0, 10).next().is_none());
+    }
+
+    #[test]
+    fn test_in_polygon() {
+        let square = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let mut reversed = square;
+        reversed.reverse();
+
+        // Both windings work, and the edges are inside
+        for points in &[square, reversed] {
+            assert!(in_polygon(points, 2.0, 2.0));
+            assert!(in_polygon(points, 4.0, 2.0));
+            assert!(in_polygon(points, 0.0, 0.0));
+            assert!(!in_polygon(points, 5.0, 2.0));
+            assert!(!in_polygon(points, 2.0, -0.5));
+        }
+
+        let triangle = [[0.0, 0.0], [4.0, 0.0], [0.0, 4.0]];
+        assert!(in_polygon(&triangle, 1.0, 1.0));
+        assert!(in_polygon(&triangle, 2.0, 2.0));
+        assert!(!in_polygon(&triangle, 2.5, 2.5));
+    }
+
+    #[test]
+    fn test_draw_number() {
+        let mut canvas = Canvas::new(12, 7);
+        canvas.draw_number(WHITE, 17, [[1.0, 0.0, 1.0], [0.0, 1.0, 6.0]]);
+        assert_eq!(rows(&canvas, [255, 255, 255]), vec![
+            "............",
+            "..#..###....",
+            ".##....#....",
+            "..#....#....",
+            "..#....#....",
+            ".###...#....",
+            "............",
+        ]);
+
+        // At zoom 2, the pixels of the digits are rounded up to 3x3 pixels of the image
+        let mut canvas = Canvas::new(9, 15);
+        canvas.draw_number(WHITE, 4, [[2.0, 0.0, 0.0], [0.0, 2.0, 15.0]]);
+        assert_eq!(rows(&canvas, [255, 255, 255]), vec![
+            "###...###",
+            "###...###",
+            "###...###",
+            "###...###",
+            "###...###",
+            "###...###",
+            "#########",
+            "#########",
+            "#########",
+            "......###",
+            "......###",
+            "......###",
+            "......###",
+            "......###",
+            "......###",
+        ]);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut world = World::parse("5\n4\n# # # # #\n # + 5 . #\n# . - . #\n # # # # #\n".as_bytes()).unwrap();
+        world.populate();
+        let canvas = render(&world, Markers::Hidden, 1.0);
+        assert_eq!((canvas.width, canvas.height), (110, 65));
+
+        // The world color, then the fill of a rocky or clear cell
+        let rocky = [49, 49, 49];
+        let clear = [95, 95, 95];
+        assert_eq!(pixel(&canvas, 10, 10), rocky);
+        assert_eq!(pixel(&canvas, 80, 25), clear);
+
+        // The red anthill at (1, 1), with its border and its fill over the border color
+        assert_eq!(pixel(&canvas, 30, 25), [128, 0, 0]);
+        assert_eq!(pixel(&canvas, 40, 30), [166, 77, 77]);
+
+        // The ants face right from the center of their cells
+        assert_eq!(pixel(&canvas, 45, 25), [128, 0, 0]);
+        assert_eq!(pixel(&canvas, 55, 40), [0, 0, 0]);
+
+        // The top-left corner of the 5 in the cell at (2, 1), which is filled twice to hide its markers
+        assert_eq!(pixel(&canvas, 54, 25), [153, 0, 255]);
+        assert_eq!(pixel(&canvas, 57, 25), [143, 143, 143]);
+
+        // Labels are left out when they would be too small
+        let canvas = render(&world, Markers::Hidden, 0.4);
+        let food_color = [153, 0, 255];
+        assert!(canvas.pixels.chunks(4).all(|p| p[..3] != food_color));
+    }
+
+    #[test]
+    fn test_gif_frame_palette() {
+        let mut canvas = Canvas::new(4, 2);
+        canvas.fill_rect(WHITE, 1.0, 0.0, 2.0, 2.0);
+        canvas.fill_rect(view::FOOD_TEXT_COLOR, 3.0, 1.0, 1.0, 1.0);
+
+        let frame = canvas.gif_frame();
+        assert_eq!(frame.palette, Some(vec![0, 0, 0, 255, 255, 255, 153, 0, 255]));
+        assert_eq!(&frame.buffer[..], &[0, 1, 1, 0, 0, 1, 1, 2]);
+        assert_eq!(gif_round_trip(&frame), canvas.pixels);
+    }
+
+    #[test]
+    fn test_gif_frame_too_many_colors() {
+        // 512 colors, which are more than a palette can hold
+        let mut canvas = Canvas::new(32, 16);
+        for y in 0..16 {
+            for x in 0..32 {
+                let color = [x as f32 / 31.0, y as f32 / 15.0, 0.5, 1.0];
+                canvas.fill_rect(color, x as f64, y as f64, 1.0, 1.0);
+            }
+        }
+
+        let frame = canvas.gif_frame();
+        assert_eq!((frame.width, frame.height), (32, 16));
+        assert_eq!(frame.buffer.len(), 32 * 16);
+        assert!(frame.palette.as_ref().unwrap().len() <= 256 * 3);
+
+        // The colors are approximated, and the image stays opaque
+        let pixels = gif_round_trip(&frame);
+        assert_eq!(pixels.len(), canvas.pixels.len());
+        let mut error = 0;
+        for (decoded, original) in pixels.chunks(4).zip(canvas.pixels.chunks(4)) {
+            assert_eq!(decoded[3], 255);
+            error += (0..3).map(|i| (decoded[i] as i32 - original[i] as i32).abs()).sum::<i32>();
+        }
+
+        assert!(error < 8 * 3 * 32 * 16, "the average error is {}", error as f64 / (3.0 * 32.0 * 16.0));
+    }
+}
@@ -0,0 +1,91 @@
+use ant_lib::{Ant, AntColor, World};
+
+/// The statistics that can be shown as a heatmap
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Heatmap {
+    Visits(AntColor),
+    Deaths,
+    FoodPickedUp,
+    FoodDropped
+}
+
+impl Heatmap {
+    /// The next heatmap to show after this one, or `None` to hide them
+    pub fn next(heatmap: Option<Heatmap>) -> Option<Heatmap> {
+        match heatmap {
+            None => Some(Heatmap::Visits(AntColor::Red)),
+            Some(Heatmap::Visits(AntColor::Red)) => Some(Heatmap::Visits(AntColor::Black)),
+            Some(Heatmap::Visits(AntColor::Black)) => Some(Heatmap::Deaths),
+            Some(Heatmap::Deaths) => Some(Heatmap::FoodPickedUp),
+            Some(Heatmap::FoodPickedUp) => Some(Heatmap::FoodDropped),
+            Some(Heatmap::FoodDropped) => None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Heatmap::Visits(AntColor::Red) => "red traffic",
+            Heatmap::Visits(AntColor::Black) => "black traffic",
+            Heatmap::Deaths => "deaths",
+            Heatmap::FoodPickedUp => "food picked up",
+            Heatmap::FoodDropped => "food dropped"
+        }
+    }
+}
+
+/// Counters per cell, accumulated since the start of the game
+#[derive(Clone)]
+pub struct Stats {
+    red_visits: Vec<u32>,
+    black_visits: Vec<u32>,
+    deaths: Vec<u32>,
+    food_picked_up: Vec<u32>,
+    food_dropped: Vec<u32>
+}
+
+impl Stats {
+    pub fn new(world: &World) -> Stats {
+        let zeros = vec![0; world.cells.len()];
+        Stats {
+            red_visits: zeros.clone(),
+            black_visits: zeros.clone(),
+            deaths: zeros.clone(),
+            food_picked_up: zeros.clone(),
+            food_dropped: zeros
+        }
+    }
+
+    pub fn get(&self, heatmap: Heatmap) -> &[u32] {
+        match heatmap {
+            Heatmap::Visits(AntColor::Red) => &self.red_visits,
+            Heatmap::Visits(AntColor::Black) => &self.black_visits,
+            Heatmap::Deaths => &self.deaths,
+            Heatmap::FoodPickedUp => &self.food_picked_up,
+            Heatmap::FoodDropped => &self.food_dropped
+        }
+    }
+
+    /// Updates the counters by comparing the ants before a round with the ants after it
+    pub fn record(&mut self, before: &[Ant], after: &[Ant]) {
+        for (old, ant) in before.iter().zip(after).filter(|&(old, _)| old.alive) {
+            // Dead ants stay at the position where they died
+            if !ant.alive {
+                self.deaths[ant.position] += 1;
+                continue;
+            }
+
+            if ant.position != old.position {
+                match ant.color {
+                    AntColor::Red => self.red_visits[ant.position] += 1,
+                    AntColor::Black => self.black_visits[ant.position] += 1
+                }
+            }
+
+            match (old.has_food, ant.has_food) {
+                (false, true) => self.food_picked_up[ant.position] += 1,
+                (true, false) => self.food_dropped[ant.position] += 1,
+                _ => ()
+            }
+        }
+    }
+}
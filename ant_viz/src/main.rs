@@ -1,4 +1,6 @@
 extern crate ant_lib;
+extern crate gif;
+extern crate image;
 extern crate opengl_graphics;
 extern crate piston_window;
 extern crate structopt;
@@ -6,37 +8,75 @@ extern crate structopt;
 
 mod camera;
 mod cli;
+mod export;
+mod heatmap;
+mod timeline;
 mod view;
 
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
 use std::process;
 use std::cmp;
 
-use ant_lib::{test_data, AntColor, Instruction, Outcome, Simulator, World};
+use ant_lib::{analysis, test_data, AntColor, Instruction, Outcome, Simulator, Snapshot, World};
 use opengl_graphics::GlGraphics;
-use piston_window::{AdvancedWindow, Button, EventLoop, Input, Key, OpenGL, PistonWindow, WindowSettings};
+use piston_window::{AdvancedWindow, Button, EventLoop, Input, Key, Motion, MouseButton, OpenGL, PistonWindow, WindowSettings};
 use structopt::StructOpt;
 
 use camera::Camera;
 use cli::Options;
-use view::View;
+use export::Export;
+use heatmap::Heatmap;
+use timeline::Timeline;
+use view::{Markers, Selection, View};
 
 const SCR_WIDTH: u32 = 1024;
 const SCR_HEIGHT: u32 = 600;
 const UPS: u32 = 2;
+const ZOOM_STEP: f64 = 1.25;
+/// The distance in pixels that the cursor needs to move while pressed to start panning
+const DRAG_THRESHOLD: f64 = 4.0;
 
-enum JumpToRound {
+/// A command that takes a number, typed after the key of the command and confirmed with Enter
+#[derive(Clone, Copy)]
+enum Command {
+    JumpToRound,
+    AdvanceUntilAntActs
+}
+
+/// What the left mouse button is doing while it is held down
+#[derive(Clone, Copy)]
+enum Drag {
+    None,
+    Slider,
+    /// The button was pressed at the given point, but the cursor hasn't moved far enough to pan
+    Pending([f64; 2]),
+    Panning
+}
+
+enum Prompt {
     No,
-    Later(String),
-    Now(String)
+    Later(Command, String),
+    Now(Command, String)
 }
 
 fn main() {
     let options = Options::from_args();
-    let (red, black, world) = load(&options);
+    let mut simulator = match options.snapshot {
+        Some(ref path) => Simulator::restore(load_snapshot(path)),
+        None => {
+            let (red, black, world) = load(&options);
+            Simulator::new(world, red, black, options.rounds, options.seed)
+        }
+    };
 
-    let mut simulator = Simulator::new(world.clone(), red, black, options.rounds, options.seed);
+    if options.png_dir.is_some() || options.gif.is_some() {
+        export_frames(&mut simulator, &options);
+        return;
+    }
+
+    let mut timeline = Timeline::new(&simulator, options.keyframe_interval, options.keyframes);
     let mut partial_outcome = Outcome::default();
     let mut view = View::new(Camera::new(SCR_WIDTH as f64, SCR_HEIGHT as f64, &simulator.world));
 
@@ -44,7 +84,14 @@ fn main() {
 
     let (mut window, mut gl) = init_window();
     let mut jump_to_finish = false;
-    let mut jump_to_round = JumpToRound::No;
+    let mut prompt = Prompt::No;
+    let mut paused = false;
+    let mut title = String::new();
+    let mut cursor = [0.0, 0.0];
+    let mut drag = Drag::None;
+    // The round picked on the slider, which is only sought once per frame because mouse events
+    // arrive much faster than the game can be replayed
+    let mut slider_round = None;
     while let Some(e) = window.next() {
         // Event handling
         match e {
@@ -58,11 +105,55 @@ fn main() {
                 }
             }
 
+            Input::Press(Button::Mouse(MouseButton::Left)) => {
+                if view.on_slider(cursor[1]) {
+                    drag = Drag::Slider;
+                    slider_round = Some(view.slider_round(cursor[0], simulator.max_rounds));
+                } else {
+                    drag = Drag::Pending(cursor);
+                }
+            }
+
+            Input::Release(Button::Mouse(MouseButton::Left)) => {
+                // A click that didn't pan selects a cell
+                if let Drag::Pending(_) = drag {
+                    view.select(&simulator.world, cursor[0], cursor[1]);
+                }
+
+                drag = Drag::None;
+            }
+
+            Input::Move(Motion::MouseCursor(x, y)) => {
+                match drag {
+                    Drag::None => {}
+                    Drag::Slider => {
+                        slider_round = Some(view.slider_round(x, simulator.max_rounds));
+                    }
+                    Drag::Pending(start) => {
+                        if (x - start[0]).abs() + (y - start[1]).abs() > DRAG_THRESHOLD {
+                            view.cam.move_x(start[0] - x);
+                            view.cam.move_y(start[1] - y);
+                            drag = Drag::Panning;
+                        }
+                    }
+                    Drag::Panning => {
+                        view.cam.move_x(cursor[0] - x);
+                        view.cam.move_y(cursor[1] - y);
+                    }
+                }
+
+                cursor = [x, y];
+            }
+
+            Input::Move(Motion::MouseScroll(_, dy)) => {
+                view.cam.zoom_at(ZOOM_STEP.powf(dy), cursor[0], cursor[1]);
+            }
+
             Input::Text(s) => {
                 if s.len() == 0 {
                     // Enter key
-                    if let JumpToRound::Later(s) = jump_to_round {
-                        jump_to_round = JumpToRound::Now(s);
+                    if let Prompt::Later(command, s) = prompt {
+                        prompt = Prompt::Now(command, s);
                     }
 
                     continue;
@@ -74,17 +165,23 @@ fn main() {
                         println!("[UPDATE] rounds per update: {}", rounds_per_update);
                     }
                     '-' => {
-                        rounds_per_update /= 2;
+                        rounds_per_update = cmp::max(1, rounds_per_update / 2);
                         println!("[UPDATE] rounds per update: {}", rounds_per_update);
                     }
                     'm' => {
                         view.toggle_marks();
-                        let title = match view.show_marks {
-                            None => "AntViz".to_string(),
-                            Some(AntColor::Red) => "AntViz - Showing red markers".to_string(),
-                            Some(AntColor::Black) => "AntViz - Showing black markers".to_string()
-                        };
-                        window.set_title(title);
+                    }
+                    'z' => {
+                        view.cam.zoom_by(ZOOM_STEP);
+                    }
+                    'x' => {
+                        view.cam.zoom_by(1.0 / ZOOM_STEP);
+                    }
+                    'c' => {
+                        view.cam.fit();
+                    }
+                    'h' => {
+                        view.heatmap = Heatmap::next(view.heatmap);
                     }
                     't' => {
                         view.show_score = !view.show_score;
@@ -93,11 +190,37 @@ fn main() {
                         jump_to_finish = true;
                     }
                     'j' => {
-                        jump_to_round = JumpToRound::Later(String::new());
+                        prompt = Prompt::Later(Command::JumpToRound, String::new());
+                    }
+                    'a' => {
+                        prompt = Prompt::Later(Command::AdvanceUntilAntActs, String::new());
+                    }
+                    ' ' => {
+                        paused = !paused;
+                    }
+                    '.' => {
+                        timeline.advance(&mut simulator, 1);
+                        partial_outcome = simulator.partial_outcome();
+                    }
+                    ',' => {
+                        // Step back one round
+                        let round = simulator.round.saturating_sub(1);
+                        timeline.seek(&mut simulator, round);
+                        partial_outcome = simulator.partial_outcome();
+                    }
+                    'w' => {
+                        save_world(&simulator);
+                    }
+                    's' => {
+                        save_snapshot(&simulator);
                     }
                     x if x.is_numeric() => {
-                        if let JumpToRound::Later(ref mut s) = jump_to_round {
-                            s.push(x);
+                        match prompt {
+                            Prompt::Later(_, ref mut s) => s.push(x),
+                            // Outside of commands, numbers toggle the markers that are shown
+                            _ => if let Some(mark) = x.to_digit(10).into_iter().filter(|&d| d < 6).next() {
+                                view.toggle_marker_filter(mark as usize);
+                            }
                         }
                     }
                     _   => ()
@@ -110,37 +233,57 @@ fn main() {
             }
 
             Input::Render(args) => {
-                gl.draw(args.viewport(), |c, g| view.render(simulator.max_rounds, &simulator.world, &partial_outcome, c, g));
+                if let Some(round) = slider_round.take() {
+                    timeline.seek(&mut simulator, round);
+                    partial_outcome = simulator.partial_outcome();
+                }
+
+                let new_title = window_title(&simulator, paused, rounds_per_update * UPS, &view);
+                if new_title != title {
+                    window.set_title(new_title.clone());
+                    title = new_title;
+                }
+
+                gl.draw(args.viewport(), |c, g| {
+                    view.render(simulator.max_rounds, &simulator.world, &timeline.stats, &partial_outcome, c, g);
+                    view.render_inspector(&simulator, c, g);
+                    view.render_slider(simulator.round, simulator.max_rounds, c, g);
+                });
             }
 
             Input::Update(_) => {
                 if jump_to_finish {
                     jump_to_finish = false;
-                    partial_outcome = simulator.run();
+                    let rounds = simulator.max_rounds - simulator.round;
+                    timeline.advance(&mut simulator, rounds);
+                    partial_outcome = simulator.partial_outcome();
                     continue;
                 }
 
-                if let JumpToRound::Now(round) = jump_to_round {
-                    match round.parse::<u32>() {
-                        Ok(round) => {
-                            if round >= simulator.round {
-                                // If the round is in the future, fast forward
-                                let sim_round = simulator.round;
-                                simulator.run_rounds(round - sim_round);
-                            } else {
-                                // If the round is in the past, rerun the simulation
-                                simulator = simulator.reset(world.clone(), options.seed);
-                                simulator.run_rounds(round);
+                if let Prompt::Now(command, input) = prompt {
+                    // Without a number, the command applies to the selected ant
+                    let input = match (command, view.selection) {
+                        (Command::AdvanceUntilAntActs, Some(Selection::Ant(id))) if input.is_empty() => id.to_string(),
+                        _ => input
+                    };
+
+                    match (command, input.parse::<u32>()) {
+                        (Command::JumpToRound, Ok(round)) => {
+                            timeline.seek(&mut simulator, round);
+                        }
+                        (Command::AdvanceUntilAntActs, Ok(id)) => {
+                            if !timeline.advance_until_ant_acts(&mut simulator, id as usize) {
+                                println!("Ant {} will not act again", id);
                             }
                         }
-                        Err(e) => {
-                            println!("Error parsing round number in jump command: {}", e);
+                        (_, Err(e)) => {
+                            println!("Error parsing number in command: {}", e);
                         }
                     }
 
-                    jump_to_round = JumpToRound::No;
-                } else {
-                    simulator.run_rounds(rounds_per_update);
+                    prompt = Prompt::No;
+                } else if !paused {
+                    timeline.advance(&mut simulator, rounds_per_update);
                 }
 
                 if view.show_score {
@@ -155,6 +298,63 @@ fn main() {
 
 // --- Auxiliary code ---
 
+fn window_title(simulator: &Simulator, paused: bool, rounds_per_second: u32, view: &View) -> String {
+    let mut title = format!("AntViz - Round {}/{}", simulator.round, simulator.max_rounds);
+    if paused {
+        title.push_str(" - Paused");
+    } else {
+        title.push_str(&format!(" - {} rounds per second", rounds_per_second));
+    }
+
+    let markers = match view.show_marks {
+        Markers::Team(AntColor::Red) => Some("red markers"),
+        Markers::Team(AntColor::Black) => Some("black markers"),
+        Markers::Both => Some("markers of both teams"),
+        Markers::Hidden => None
+    };
+
+    if let Some(markers) = markers {
+        title.push_str(&format!(" - Showing {}", markers));
+        if view.marker_filter.iter().any(|&shown| !shown) {
+            let shown: Vec<_> = (0..6).filter(|&m| view.marker_filter[m]).map(|m| m.to_string()).collect();
+            title.push_str(&format!(" ({})", shown.join(" ")));
+        }
+    }
+
+    if let Some(heatmap) = view.heatmap {
+        title.push_str(&format!(" - Heatmap of {}", heatmap.name()));
+    }
+
+    title
+}
+
+fn export_frames(simulator: &mut Simulator, options: &Options) {
+    let show_marks = match &options.frame_markers[..] {
+        "none" => Markers::Hidden,
+        "red" => Markers::Team(AntColor::Red),
+        "black" => Markers::Team(AntColor::Black),
+        "both" => Markers::Both,
+        markers => fatal_error(&format!("invalid markers `{}`, expected none, red, black or both", markers))
+    };
+
+    if options.frame_scale <= 0.0 {
+        fatal_error("the frame scale must be positive");
+    }
+
+    let export = Export {
+        png_dir: options.png_dir.as_ref().map(PathBuf::from),
+        gif: options.gif.as_ref().map(PathBuf::from),
+        interval: options.frame_interval,
+        scale: options.frame_scale,
+        show_marks
+    };
+
+    match export::run(simulator, &export) {
+        Ok(frames) => println!("[SAVE] {} frames written, up to round {}", frames, simulator.round),
+        Err(e) => fatal_error(&format!("unable to write the frames: {}", e))
+    }
+}
+
 fn init_window() -> (PistonWindow, GlGraphics) {
     let opengl = OpenGL::V3_2;
     let window: Result<PistonWindow, _> =
@@ -184,18 +384,75 @@ fn open_file_or_die(path: &str) -> BufReader<File> {
                      .unwrap_or_else(|_| fatal_error(&format!("unable to open file: {}", path)))
 }
 
+fn save_world(simulator: &Simulator) {
+    let path = format!("world-{}.world", simulator.round);
+    let result = File::create(&path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        simulator.world.to_starting_world().write_to(&mut writer)?;
+        writer.flush()
+    });
+
+    match result {
+        Ok(()) => println!("[SAVE] world written to {}", path),
+        Err(e) => println!("Error writing world to {}: {}", path, e)
+    }
+}
+
+fn save_snapshot(simulator: &Simulator) {
+    let path = format!("game-{}.snapshot", simulator.round);
+    let result = File::create(&path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        simulator.snapshot().write_to(&mut writer)?;
+        writer.flush()
+    });
+
+    match result {
+        Ok(()) => println!("[SAVE] game written to {}", path),
+        Err(e) => println!("Error writing game to {}: {}", path, e)
+    }
+}
+
+fn load_snapshot(path: &str) -> Snapshot {
+    Snapshot::parse(open_file_or_die(path)).unwrap_or_else(|e| {
+        fatal_error(&format!("unable to load snapshot from {}:\n{}", path, e))
+    })
+}
+
+fn load_instructions(path: &str) -> Vec<Instruction> {
+    let instrs = Instruction::parse(open_file_or_die(path)).unwrap_or_else(|e| {
+        fatal_error(&format!("invalid instructions in {}:\n{}", path, e))
+    });
+
+    let diagnostics = analysis::analyze(&instrs);
+    for diagnostic in &diagnostics {
+        println!("{}: {}", path, diagnostic);
+    }
+
+    if diagnostics.iter().any(|d| d.is_error()) {
+        fatal_error(&format!("the instructions in {} would crash the simulator", path));
+    }
+
+    instrs
+}
+
+fn load_world(path: &str) -> World {
+    World::parse(open_file_or_die(path)).unwrap_or_else(|e| {
+        fatal_error(&format!("invalid world in {}:\n{}", path, e))
+    })
+}
+
 fn load(options: &Options) -> (Vec<Instruction>, Vec<Instruction>, World) {
-    let red = options.red.as_ref().map(|p| Instruction::parse(open_file_or_die(p))).unwrap_or_else(|| {
+    let red = options.red.as_ref().map(|p| load_instructions(p)).unwrap_or_else(|| {
         println!("No file specified for red ant instructions. Using defaults.");
         test_data::ant1()
     });
 
-    let black = options.black.as_ref().map(|p| Instruction::parse(open_file_or_die(p))).unwrap_or_else(|| {
+    let black = options.black.as_ref().map(|p| load_instructions(p)).unwrap_or_else(|| {
         println!("No file specified for black ant instructions. Using defaults.");
         test_data::ant1()
     });
 
-    let world = options.world.as_ref().map(|p| World::parse(open_file_or_die(p))).unwrap_or_else(|| {
+    let world = options.world.as_ref().map(|p| load_world(p)).unwrap_or_else(|| {
         println!("No world file specified. Using default world.");
         test_data::sample0()
     });
@@ -0,0 +1,200 @@
+use ant_lib::{Ant, GameState, Simulator};
+
+use heatmap::Stats;
+
+/// States of the game taken every few rounds, so it can be rewound without replaying it
+///
+/// The amount of keyframes is bounded: when the budget is exhausted, every other keyframe is
+/// dropped and the interval between them is doubled, so the keyframes always cover the whole game.
+pub struct Timeline {
+    /// Sorted by round, the first one being the start of the game
+    keyframes: Vec<Keyframe>,
+    interval: u32,
+    budget: usize,
+    /// The statistics of the game up to the current round
+    pub stats: Stats,
+    /// The ants before the last round, kept around to avoid allocating every round
+    previous_ants: Vec<Ant>
+}
+
+/// The brains never change, so they are left out of the keyframes
+struct Keyframe {
+    state: GameState,
+    stats: Stats
+}
+
+impl Timeline {
+    pub fn new(simulator: &Simulator, interval: u32, budget: usize) -> Timeline {
+        let stats = Stats::new(&simulator.world);
+        Timeline {
+            keyframes: vec![Keyframe { state: simulator.state(), stats: stats.clone() }],
+            interval: interval.max(1),
+            budget: budget.max(2),
+            stats,
+            previous_ants: Vec::new()
+        }
+    }
+
+    /// Runs the given amount of rounds, without going past the last round of the game
+    pub fn advance(&mut self, simulator: &mut Simulator, rounds: u32) {
+        let target = simulator.round.saturating_add(rounds).min(simulator.max_rounds);
+        while simulator.round < target {
+            self.step(simulator, None);
+        }
+    }
+
+    /// Brings the game to the given round, which may be in the past
+    pub fn seek(&mut self, simulator: &mut Simulator, round: u32) {
+        let round = round.min(simulator.max_rounds);
+        if round < simulator.round {
+            // Start from the last keyframe before the target round
+            let keyframe = self.keyframes.iter().rev().find(|k| k.state.round <= round).unwrap_or(&self.keyframes[0]);
+            simulator.set_state(&keyframe.state);
+            self.stats = keyframe.stats.clone();
+        }
+
+        let rounds = round.saturating_sub(simulator.round);
+        self.advance(simulator, rounds);
+    }
+
+    /// Runs rounds until the ant with the given id runs an instruction
+    ///
+    /// Returns false if that doesn't happen before the end of the game or the death of the ant
+    pub fn advance_until_ant_acts(&mut self, simulator: &mut Simulator, id: usize) -> bool {
+        while simulator.round < simulator.max_rounds {
+            match simulator.world.ants.get(id) {
+                Some(ant) if ant.alive => (),
+                _ => return false
+            }
+
+            // The ant may be killed later in the round in which it acts, which still counts
+            if self.step(simulator, Some(id)) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Runs a single round, updating the statistics and the keyframes
+    ///
+    /// Returns whether the given ant, if any, ran an instruction in that round.
+    fn step(&mut self, simulator: &mut Simulator, watched: Option<usize>) -> bool {
+        self.previous_ants.clone_from(&simulator.world.ants);
+        let acted = match watched {
+            Some(id) => simulator.one_round_for(id),
+            None => {
+                simulator.one_round();
+                false
+            }
+        };
+
+        self.stats.record(&self.previous_ants, &simulator.world.ants);
+        self.record(simulator);
+        acted
+    }
+
+    fn record(&mut self, simulator: &Simulator) {
+        let start = self.keyframes[0].state.round;
+        let last = self.keyframes[self.keyframes.len() - 1].state.round;
+        if simulator.round <= last || (simulator.round - start) % self.interval != 0 {
+            return;
+        }
+
+        if self.keyframes.len() == self.budget {
+            self.interval *= 2;
+            let interval = self.interval;
+            self.keyframes.retain(|k| (k.state.round - start) % interval == 0);
+        }
+
+        // After thinning, the current round may no longer be on the grid of keyframes
+        if (simulator.round - start) % self.interval == 0 {
+            self.keyframes.push(Keyframe { state: simulator.state(), stats: self.stats.clone() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ant_lib::{test_data, AntColor, Simulator};
+
+    use heatmap::Heatmap;
+    use super::Timeline;
+
+    fn simulator() -> Simulator {
+        Simulator::new(test_data::sample0(), test_data::ant1(), test_data::ant1(), 10_000, 12345)
+    }
+
+    fn keyframe_rounds(timeline: &Timeline) -> Vec<u32> {
+        timeline.keyframes.iter().map(|k| k.state.round).collect()
+    }
+
+    /// The full state of a game, to compare simulators
+    fn state(simulator: &Simulator) -> Vec<u8> {
+        let mut output = Vec::new();
+        simulator.snapshot().write_to(&mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn test_thinning_with_budget_2() {
+        let mut simulator = simulator();
+        let mut timeline = Timeline::new(&simulator, 10, 2);
+        timeline.advance(&mut simulator, 10);
+        assert_eq!(keyframe_rounds(&timeline), vec![0, 10]);
+
+        // Every thinning keeps only the start, and the interval doubles until the next keyframe
+        timeline.advance(&mut simulator, 990);
+        assert_eq!(keyframe_rounds(&timeline), vec![0, 640]);
+        assert_eq!(timeline.interval, 640);
+    }
+
+    #[test]
+    fn test_seek_back_and_advance() {
+        let mut simulator = simulator();
+        let mut timeline = Timeline::new(&simulator, 10, 100);
+        timeline.advance(&mut simulator, 100);
+
+        timeline.seek(&mut simulator, 35);
+        let mut replayed = self::simulator();
+        replayed.run_rounds(35);
+        assert_eq!(state(&simulator), state(&replayed));
+
+        // Advancing past the existing keyframes doesn't record them again
+        timeline.advance(&mut simulator, 115);
+        let expected: Vec<_> = (0..16).map(|i| i * 10).collect();
+        assert_eq!(keyframe_rounds(&timeline), expected);
+
+        // The game and its statistics are the same as without going back
+        let mut straight = self::simulator();
+        let mut straight_timeline = Timeline::new(&straight, 10, 100);
+        straight_timeline.advance(&mut straight, 150);
+        assert_eq!(state(&simulator), state(&straight));
+        for &heatmap in &[Heatmap::Visits(AntColor::Red), Heatmap::Deaths, Heatmap::FoodPickedUp, Heatmap::FoodDropped] {
+            assert_eq!(timeline.stats.get(heatmap), straight_timeline.stats.get(heatmap));
+        }
+    }
+
+    #[test]
+    fn test_start_after_round_0() {
+        let mut simulator = simulator();
+        simulator.run_rounds(37);
+        let start = state(&simulator);
+
+        // The keyframes are aligned to the first round, and thinning keeps it
+        let mut timeline = Timeline::new(&simulator, 10, 3);
+        timeline.advance(&mut simulator, 20);
+        assert_eq!(keyframe_rounds(&timeline), vec![37, 47, 57]);
+        timeline.advance(&mut simulator, 10);
+        assert_eq!(keyframe_rounds(&timeline), vec![37, 57]);
+        timeline.advance(&mut simulator, 10);
+        assert_eq!(keyframe_rounds(&timeline), vec![37, 57, 77]);
+        timeline.advance(&mut simulator, 40);
+        assert_eq!(keyframe_rounds(&timeline), vec![37, 77, 117]);
+
+        // Seeking before the start goes back to the start
+        timeline.seek(&mut simulator, 0);
+        assert_eq!(simulator.round, 37);
+        assert_eq!(state(&simulator), start);
+    }
+}
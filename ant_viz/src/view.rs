@@ -1,139 +1,126 @@
 use std::env;
 
-use ant_lib::{AntColor, AntDirection, Cell, Outcome, World};
+use ant_lib::{AntColor, AntDirection, Cell, Outcome, Simulator, World};
 use opengl_graphics::GlGraphics;
 use opengl_graphics::glyph_cache::GlyphCache;
-use piston_window::{self, Context, Transformed};
+use piston_window::{self, triangulation, Context, Graphics, Transformed};
+use piston_window::math::Matrix2d;
 
 use camera::Camera;
+use heatmap::{Heatmap, Stats};
 
 pub const CELL_WIDTH: f64 = 20.0;
 const INNER_CELL_WIDTH: f64 = CELL_WIDTH - 2.0 * CELL_BORDER;
 const CELL_BORDER: f64 = 1.0;
 pub const ROW_HEIGHT: f64 = 3.0 * CELL_WIDTH / 4.0;
+const SLIDER_HEIGHT: f64 = 12.0;
+/// The color drawn over the area of the world, on a black background
+pub const WORLD_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.1];
+pub const FOOD_TEXT_COLOR: [f32; 4] = [0.6, 0.0, 1.0, 1.0];
+
+const MARKER_COLORS: [[f32; 4]; 6] = [
+    [1.0, 1.0, 1.0, 0.8],
+    [1.0, 0.0, 1.0, 0.8],
+    [1.0, 1.0, 0.0, 0.8],
+    [0.0, 1.0, 1.0, 0.8],
+    [0.0, 0.0, 1.0, 0.8],
+    [0.0, 1.0, 0.0, 0.8],
+];
+const MARKER_DOT: f64 = 3.0;
+const HEAT_LEVELS: f64 = 32.0;
+
+/// Which markers are shown
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Markers {
+    Hidden,
+    /// The markers of one team, as triangles pointing in a different direction for each number
+    Team(AntColor),
+    /// The markers of both teams, as dots on the left half of the cell for red and on the right
+    /// half for black
+    Both
+}
+
+const PANEL_WIDTH: f64 = 330.0;
+const PANEL_LINE_HEIGHT: f64 = 18.0;
 
-// const MARKER_COLORS: [[f32; 4]; 6] = [
-//     [1.0, 1.0, 1.0, 0.5],
-//     [1.0, 0.0, 1.0, 0.5],
-//     [1.0, 1.0, 0.0, 0.5],
-//     [0.0, 1.0, 1.0, 0.5],
-//     [0.0, 0.0, 1.0, 0.5],
-//     [0.0, 1.0, 0.0, 0.5],
-// ];
+/// What is shown in the inspector panel
+#[derive(Clone, Copy)]
+pub enum Selection {
+    Cell(usize),
+    /// The ant with the given id, which is followed as it moves
+    Ant(usize)
+}
 
 pub struct View {
     pub cam: Camera,
     pub font: GlyphCache<'static>,
-    pub show_marks: Option<AntColor>,
-    pub show_score: bool
+    pub show_marks: Markers,
+    /// The marker numbers that are shown, the rest being hidden
+    pub marker_filter: [bool; 6],
+    pub show_score: bool,
+    pub selection: Option<Selection>,
+    pub heatmap: Option<Heatmap>
 }
 
 impl View {
     pub fn new(cam: Camera) -> View {
         let exe_directory = env::current_exe().unwrap().parent().unwrap().to_owned();
         let font = GlyphCache::new(exe_directory.join("resources/FiraMono-Bold.ttf")).unwrap();
-        View { cam, font, show_marks: None, show_score: false }
+        View { cam, font, show_marks: Markers::Hidden, marker_filter: [true; 6], show_score: false, selection: None, heatmap: None }
     }
 
     pub fn toggle_marks(&mut self) {
-        let next_color = match self.show_marks {
-            None => Some(AntColor::Red),
-            Some(AntColor::Red) => Some(AntColor::Black),
-            Some(AntColor::Black) => None,
+        let next = match self.show_marks {
+            Markers::Hidden => Markers::Team(AntColor::Red),
+            Markers::Team(AntColor::Red) => Markers::Team(AntColor::Black),
+            Markers::Team(AntColor::Black) => Markers::Both,
+            Markers::Both => Markers::Hidden
         };
 
-        self.show_marks = next_color;
+        self.show_marks = next;
     }
 
-    pub fn render(&mut self, max_rounds: u32, world: &World, outcome: &Outcome, c: Context, g: &mut GlGraphics) {
+    pub fn render(&mut self, max_rounds: u32, world: &World, stats: &Stats, outcome: &Outcome, c: Context, g: &mut GlGraphics) {
         let abs_trans = c.transform;
-        let trans = c.transform.trans(-self.cam.x, -self.cam.y);
+        let trans = self.cam.transform(c.transform);
 
         piston_window::clear([0.0, 0.0, 0.0, 1.0], g);
-        piston_window::rectangle([1.0, 1.0, 1.0, 0.1],
+        piston_window::rectangle(WORLD_COLOR,
                                  [0.0, 0.0, self.cam.world_width, self.cam.world_height],
                                  trans,
                                  g);
 
-        // The cells
-        let outer_polygon = cell_polygon(CELL_WIDTH);
-        let inner_polygon = cell_polygon(INNER_CELL_WIDTH);
-        let ant_polygon = ant_polygon();
-        let marker_polygon = marker_polygon();
-        for (i, cell) in world.cells.iter().enumerate() {
-            let (x, y) = World::index_to_coords(world.width, i);
-            let x_offset = if y % 2 != 0 { CELL_WIDTH / 2.0 } else { 0.0 };
-
-            // Convert the coordinates to pixel coordinates
-            let (x, y) = (x_offset + x as f64 * CELL_WIDTH, y as f64 * ROW_HEIGHT);
-            let (center_x, center_y) = (x + CELL_WIDTH / 2.0, y + ROW_HEIGHT / 3.0 * 2.0);
-
-            let (border_color, fill_color) = cell_color(cell);
-
-            // The outer border
-            piston_window::polygon(
-                border_color,
-                &outer_polygon,
-                trans.trans(x, y),
-                g);
-
-            // The fill of the polygon
-            piston_window::polygon(
-                fill_color,
-                &inner_polygon,
-                trans.trans(x + CELL_BORDER, y + CELL_BORDER),
-                g);
-
-            // Markers per color
-            if let Some(markers) = self.show_marks.map(|color| cell.markers(color)) {
-                for mark in markers.iter() {
-                    // Triangle in the right direction
-                    piston_window::polygon(
-                        [1.0, 1.0, 0.0, 0.5],
-                        &marker_polygon,
-                        trans.trans(center_x, center_y).rot_rad(rotation(mark)),
-                        g);
-                }
-            }
+        // Only the visible cells are drawn, and polygons with the same shape and color are batched
+        // into a single draw call
+        let width = world.width;
+        let (columns, rows) = self.cam.visible_cells(world);
+        let visible: Vec<_> = rows.flat_map(|y| {
+            columns.clone().map(move |x| World::coords_to_index(width, x, y))
+        }).collect();
+
+        let scene = Scene::new(world, &visible, self.show_marks, &self.marker_filter, trans);
+        for layer in &scene.cells {
+            layer.draw(&c, g);
+        }
 
-            // Food
-            if cell.food > 0 {
-                piston_window::polygon(
-                    fill_color,
-                    &inner_polygon,
-                    trans.trans(x + CELL_BORDER, y + CELL_BORDER),
-                    g);
-
-                piston_window::text([0.6, 0.0, 1.0, 1.0],
-                                    10,
-                                    &cell.food.to_string(),
+        // The text is rendered at the zoomed size so it stays sharp
+        if let Some(font_size) = food_font_size(self.cam.zoom) {
+            for &(food, label_trans) in &scene.food_labels {
+                piston_window::text(FOOD_TEXT_COLOR,
+                                    font_size,
+                                    &food.to_string(),
                                     &mut self.font,
-                                    trans.trans(x + 0.2 * CELL_WIDTH, y + ROW_HEIGHT),
+                                    label_trans.zoom(1.0 / self.cam.zoom),
                                     g);
             }
+        }
 
-            // Ants
-            if let Some(ref ant) = cell.ant {
-                let ant_trans = trans.trans(center_x, center_y).rot_rad(ant_rotation(ant.direction));
-                piston_window::polygon(
-                    ant_color(ant.color),
-                    &ant_polygon,
-                    ant_trans,
-                    g);
-
-                // Food is shown as a white rectangle on the head of the ant
-                if ant.has_food {
-                    piston_window::rectangle(
-                        [1.0, 1.0, 1.0, 1.0],
-                        [0.0, 0.0, 4.0, 4.0],
-                        ant_trans.trans(CELL_WIDTH / 2.0 - 2.0, -2.0),
-                        g);
-                }
-            }
-
-
+        for layer in &scene.ants {
+            layer.draw(&c, g);
         }
 
+        self.render_heatmap(stats, world, &visible, c, g);
+
         if self.show_score {
             let red_score_str = outcome.red_score.to_string();
             let red_alive_str = outcome.red_alive.to_string();
@@ -205,6 +192,351 @@ impl View {
             print(&rounds_str, &mut trans);
         }
     }
+
+    /// Shows or hides the markers with the given number
+    pub fn toggle_marker_filter(&mut self, mark: usize) {
+        self.marker_filter[mark] = !self.marker_filter[mark];
+    }
+
+    /// Colors each cell according to the current heatmap, relative to the highest value
+    fn render_heatmap(&self, stats: &Stats, world: &World, visible: &[usize], c: Context, g: &mut GlGraphics) {
+        let values = match self.heatmap {
+            Some(heatmap) => stats.get(heatmap),
+            None => return
+        };
+
+        // A logarithmic scale keeps the cells with few events visible next to the busiest ones.
+        // The scale is the same regardless of which cells are visible.
+        let max = values.iter().cloned().max().unwrap_or(0);
+        let scale = (max as f64).ln_1p();
+        let trans = self.cam.transform(c.transform);
+        let mut layer = Layer { polygon: cell_polygon(INNER_CELL_WIDTH).to_vec(), batches: Batches::new() };
+        for &i in visible.iter().filter(|&&i| values[i] > 0) {
+            // Levels are rounded so that cells can be batched by color
+            let level = ((values[i] as f64).ln_1p() / scale * HEAT_LEVELS).round() / HEAT_LEVELS;
+            let (x, y) = cell_origin(world.width, i);
+            layer.batches.add(heat_color(level), trans.trans(x + CELL_BORDER, y + CELL_BORDER));
+        }
+
+        layer.draw(&c, g);
+    }
+
+    /// The index of the cell at the given screen coordinates, if any
+    pub fn cell_at(&self, world: &World, x: f64, y: f64) -> Option<usize> {
+        let (x, y) = self.cam.to_world(x, y);
+
+        // Rows overlap, so the point may belong to a cell of the previous row
+        let row = (y / ROW_HEIGHT).floor() as isize;
+        for cy in (row - 1...row).filter(|&cy| cy >= 0 && (cy as usize) < world.height) {
+            let x_offset = if cy % 2 != 0 { CELL_WIDTH / 2.0 } else { 0.0 };
+            let cx = ((x - x_offset) / CELL_WIDTH).floor() as isize;
+            if cx < 0 || cx as usize >= world.width {
+                continue;
+            }
+
+            let i = World::coords_to_index(world.width, cx as usize, cy as usize);
+            let (left, top) = cell_origin(world.width, i);
+            if in_hexagon(x - left, y - top) {
+                return Some(i);
+            }
+        }
+
+        None
+    }
+
+    /// Selects the cell at the given screen coordinates, or the ant in it
+    pub fn select(&mut self, world: &World, x: f64, y: f64) {
+        self.selection = self.cell_at(world, x, y).map(|i| match world.ant_at(i) {
+            Some(ant) => Selection::Ant(ant.id as usize),
+            None => Selection::Cell(i)
+        });
+    }
+
+    /// Highlights the selected cell and shows what is in it in a panel at the top right corner
+    pub fn render_inspector(&mut self, simulator: &Simulator, c: Context, g: &mut GlGraphics) {
+        let world = &simulator.world;
+        let (cell, ant) = match self.selection {
+            None => return,
+            Some(Selection::Cell(i)) => (Some(i), world.ant_at(i)),
+            Some(Selection::Ant(id)) => {
+                let ant = &world.ants[id];
+                (if ant.alive { Some(ant.position) } else { None }, Some(ant))
+            }
+        };
+
+        let mut lines = Vec::new();
+        if let Some(i) = cell {
+            let (x, y) = World::index_to_coords(world.width, i);
+            let cell = &world.cells[i];
+            lines.push(format!("Cell ({}, {})", x, y));
+            lines.push(format!("Food: {}", cell.food));
+            lines.push(format!("Anthill: {}", match (cell.is_rocky, cell.anthill) {
+                (true, _) => "none (rock)",
+                (false, Some(color)) => color.name(),
+                (false, None) => "none"
+            }));
+            lines.push(format!("Red markers: {}", marker_list(cell, AntColor::Red)));
+            lines.push(format!("Black markers: {}", marker_list(cell, AntColor::Black)));
+
+            // Highlight the cell
+            let (left, top) = cell_origin(world.width, i);
+            let trans = self.cam.transform(c.transform).trans(left, top);
+            let outline = cell_polygon(CELL_WIDTH);
+            for j in 0..outline.len() {
+                let (from, to) = (outline[j], outline[(j + 1) % outline.len()]);
+                piston_window::line([1.0, 1.0, 0.0, 1.0], 1.0, [from[0], from[1], to[0], to[1]], trans, g);
+            }
+        }
+
+        match ant {
+            Some(ant) if ant.alive => {
+                let instructions = simulator.instructions(ant.color);
+                lines.push(format!("Ant {} ({})", ant.id, ant.color.name()));
+                lines.push(format!("State: {}", ant.state));
+                lines.push(format!("{}", instructions[ant.state as usize]));
+                lines.push(format!("Resting: {}", ant.resting));
+                lines.push(format!("Direction: {:?}", ant.direction));
+                lines.push(format!("Has food: {}", if ant.has_food { "yes" } else { "no" }));
+            }
+            Some(ant) => lines.push(format!("Ant {} ({}) is dead", ant.id, ant.color.name())),
+            None => lines.push("No ant".to_string())
+        }
+
+        let left = self.cam.scr_width - PANEL_WIDTH;
+        let height = PANEL_LINE_HEIGHT * (lines.len() as f64 + 0.5);
+        piston_window::rectangle([0.0, 0.0, 0.0, 0.8],
+                                 [left, 0.0, PANEL_WIDTH, height],
+                                 c.transform,
+                                 g);
+
+        for (n, line) in lines.iter().enumerate() {
+            let trans = c.transform.trans(left + 8.0, PANEL_LINE_HEIGHT * (n as f64 + 1.0));
+            piston_window::text([1.0, 1.0, 1.0, 1.0], 12, line, &mut self.font, trans, g);
+        }
+    }
+
+    /// Draws the timeline at the bottom of the screen, filled up to the current round
+    pub fn render_slider(&self, round: u32, max_rounds: u32, c: Context, g: &mut GlGraphics) {
+        let top = self.cam.scr_height - SLIDER_HEIGHT;
+        let progress = if max_rounds == 0 { 1.0 } else { round as f64 / max_rounds as f64 };
+
+        piston_window::rectangle([0.0, 0.0, 0.0, 0.6],
+                                 [0.0, top, self.cam.scr_width, SLIDER_HEIGHT],
+                                 c.transform,
+                                 g);
+        piston_window::rectangle([1.0, 1.0, 0.0, 0.6],
+                                 [0.0, top + 2.0, progress * self.cam.scr_width, SLIDER_HEIGHT - 4.0],
+                                 c.transform,
+                                 g);
+    }
+
+    /// Whether the given vertical screen coordinate is on the timeline
+    pub fn on_slider(&self, y: f64) -> bool {
+        y >= self.cam.scr_height - SLIDER_HEIGHT
+    }
+
+    /// The round corresponding to the given horizontal screen coordinate of the timeline
+    pub fn slider_round(&self, x: f64, max_rounds: u32) -> u32 {
+        let fraction = (x / self.cam.scr_width).max(0.0).min(1.0);
+        (fraction * max_rounds as f64).round() as u32
+    }
+}
+
+/// The font size of the amount of food in a cell at the given zoom, or `None` if it would be too
+/// small to be read
+pub fn food_font_size(zoom: f64) -> Option<u32> {
+    let size = (10.0 * zoom).round() as u32;
+    if size >= 5 { Some(size) } else { None }
+}
+
+/// The size in pixels of the whole world
+pub fn world_size(world: &World) -> (f64, f64) {
+    (CELL_WIDTH * world.width as f64 + CELL_WIDTH / 2.0, ROW_HEIGHT * world.height as f64 + ROW_HEIGHT / 3.0)
+}
+
+/// The position in pixels of the top-left corner of the cell with the given index
+fn cell_origin(world_width: usize, i: usize) -> (f64, f64) {
+    let (x, y) = World::index_to_coords(world_width, i);
+    let x_offset = if y % 2 != 0 { CELL_WIDTH / 2.0 } else { 0.0 };
+    (x_offset + x as f64 * CELL_WIDTH, y as f64 * ROW_HEIGHT)
+}
+
+/// The shapes of a set of cells, grouped in layers that are drawn in order
+///
+/// Each layer is drawn for all the cells before the next one: first the borders, then the fills,
+/// the markers, the fills that hide the markers under the food, the amounts of food and finally
+/// the ants and the food they carry. Cells don't overlap, so the result looks the same as drawing
+/// every layer of a cell before moving on to the next cell, except that an ant on the edge of its
+/// cell is never covered by the fill of a neighbouring cell.
+///
+/// This is shared by the window and the offscreen renderer, so both show the game the same way.
+pub struct Scene {
+    /// The hexagons, the markers and, in cells with food, a second fill that covers the markers
+    pub cells: Vec<Layer>,
+    /// The amount of food in a cell and the transform of the bottom-left corner of its label
+    pub food_labels: Vec<(u16, Matrix2d)>,
+    /// The ants and the food they carry
+    pub ants: Vec<Layer>
+}
+
+impl Scene {
+    /// Builds the scene of the given cells, with `trans` being the transform of the world origin
+    pub fn new(world: &World, cells: &[usize], show_marks: Markers, marker_filter: &[bool; 6], trans: Matrix2d) -> Scene {
+        let mut borders = Batches::new();
+        let mut fills = Batches::new();
+        let mut markers = Batches::new();
+        let mut food_fills = Batches::new();
+        let mut ants = Batches::new();
+        let mut carried_food = Batches::new();
+        let mut food_labels = Vec::new();
+        for &i in cells {
+            let cell = &world.cells[i];
+            let (x, y) = cell_origin(world.width, i);
+            let (center_x, center_y) = (x + CELL_WIDTH / 2.0, y + ROW_HEIGHT / 3.0 * 2.0);
+
+            let (border_color, fill_color) = cell_color(cell);
+            borders.add(border_color, trans.trans(x, y));
+            fills.add(fill_color, trans.trans(x + CELL_BORDER, y + CELL_BORDER));
+
+            // Markers per color
+            match show_marks {
+                Markers::Hidden => (),
+                Markers::Team(color) => {
+                    for mark in cell.markers(color).iter().filter(|&m| marker_filter[m as usize]) {
+                        // Triangle in the right direction
+                        markers.add(MARKER_COLORS[mark as usize], trans.trans(center_x, center_y).rot_rad(rotation(mark)));
+                    }
+                }
+                Markers::Both => {
+                    for (half, &color) in [AntColor::Red, AntColor::Black].iter().enumerate() {
+                        for mark in cell.markers(color).iter().filter(|&m| marker_filter[m as usize]) {
+                            // Two columns of three dots in each half
+                            let dx = (half as f64 * 2.0 + (mark % 2) as f64 - 2.0) * (MARKER_DOT + 1.0) + 0.5;
+                            let dy = ((mark / 2) as f64 - 1.5) * (MARKER_DOT + 1.0) + 0.5;
+                            markers.add(MARKER_COLORS[mark as usize], trans.trans(center_x + dx, center_y + dy));
+                        }
+                    }
+                }
+            }
+
+            // Food, which hides the markers below its amount
+            if cell.food > 0 {
+                food_fills.add(fill_color, trans.trans(x + CELL_BORDER, y + CELL_BORDER));
+                food_labels.push((cell.food, trans.trans(x + 0.2 * CELL_WIDTH, y + ROW_HEIGHT)));
+            }
+
+            // Ants
+            if let Some(ant) = world.ant_at(i) {
+                let ant_trans = trans.trans(center_x, center_y).rot_rad(ant_rotation(ant.direction));
+                ants.add(ant_color(ant.color), ant_trans);
+
+                // Food is shown as a white rectangle on the head of the ant
+                if ant.has_food {
+                    carried_food.add([1.0, 1.0, 1.0, 1.0], ant_trans.trans(CELL_WIDTH / 2.0 - 2.0, -2.0));
+                }
+            }
+        }
+
+        let marker_polygon = match show_marks {
+            Markers::Both => square_polygon(MARKER_DOT).to_vec(),
+            _ => marker_polygon().to_vec()
+        };
+
+        Scene {
+            cells: vec![
+                Layer { polygon: cell_polygon(CELL_WIDTH).to_vec(), batches: borders },
+                Layer { polygon: cell_polygon(INNER_CELL_WIDTH).to_vec(), batches: fills },
+                Layer { polygon: marker_polygon, batches: markers },
+                Layer { polygon: cell_polygon(INNER_CELL_WIDTH).to_vec(), batches: food_fills }
+            ],
+            food_labels,
+            ants: vec![
+                Layer { polygon: ant_polygon().to_vec(), batches: ants },
+                Layer { polygon: square_polygon(4.0).to_vec(), batches: carried_food }
+            ]
+        }
+    }
+}
+
+/// Copies of a convex polygon, of up to 6 points, each with its own color and transform
+pub struct Layer {
+    pub polygon: Vec<[f64; 2]>,
+    pub batches: Batches
+}
+
+impl Layer {
+    /// Draws the polygons with a draw call per color
+    fn draw(&self, c: &Context, g: &mut GlGraphics) {
+        let polygon = &self.polygon;
+        for &(ref color, ref transforms) in &self.batches.batches {
+            g.tri_list(&c.draw_state, color, |f| {
+                for &m in transforms {
+                    // Split the polygon into a fan of triangles around its first point
+                    let mut vertices = [[0.0; 2]; 12];
+                    let mut n = 0;
+                    for k in 1..polygon.len() - 1 {
+                        for p in &[polygon[0], polygon[k], polygon[k + 1]] {
+                            vertices[n] = [triangulation::tx(m, p[0], p[1]), triangulation::ty(m, p[0], p[1])];
+                            n += 1;
+                        }
+                    }
+
+                    f(&vertices[..n]);
+                }
+            });
+        }
+    }
+}
+
+/// Transforms grouped by color
+pub struct Batches {
+    pub batches: Vec<([f32; 4], Vec<Matrix2d>)>
+}
+
+impl Batches {
+    fn new() -> Batches {
+        Batches { batches: Vec::new() }
+    }
+
+    fn add(&mut self, color: [f32; 4], transform: Matrix2d) {
+        // Fully transparent polygons don't need to be drawn
+        if color[3] == 0.0 {
+            return;
+        }
+
+        // There are only a few colors, so a linear search is fast enough
+        match self.batches.iter().position(|&(c, _)| c == color) {
+            Some(i) => self.batches[i].1.push(transform),
+            None => self.batches.push((color, vec![transform]))
+        }
+    }
+}
+
+/// Whether a point, relative to the top-left corner of a cell, is inside of its hexagon
+fn in_hexagon(x: f64, y: f64) -> bool {
+    // The top and bottom edges are zigzags, whose peaks are at the middle of the cell
+    let slope = x.min(CELL_WIDTH - x) / 2.0;
+    x >= 0.0 && x <= CELL_WIDTH && y >= CELL_WIDTH / 4.0 - slope && y <= 3.0 * CELL_WIDTH / 4.0 + slope
+}
+
+/// A color going from blue to yellow to red as the given value goes from 0 to 1
+fn heat_color(t: f64) -> [f32; 4] {
+    let t = t.max(0.0).min(1.0) as f32;
+    if t < 0.5 {
+        [2.0 * t, 2.0 * t, 1.0 - 2.0 * t, 0.7]
+    } else {
+        [1.0, 2.0 - 2.0 * t, 0.0, 0.7]
+    }
+}
+
+/// The numbers of the markers of the given color in a cell, like `0 2 5`
+fn marker_list(cell: &Cell, color: AntColor) -> String {
+    let markers: Vec<_> = cell.markers(color).iter().map(|m| m.to_string()).collect();
+    if markers.is_empty() {
+        "-".to_string()
+    } else {
+        markers.join(" ")
+    }
 }
 
 fn ant_color(color: AntColor) -> [f32; 4] {
@@ -275,6 +607,10 @@ fn cell_polygon(width: f64) -> [[f64; 2]; 6] {
     ]
 }
 
+fn square_polygon(side: f64) -> [[f64; 2]; 4] {
+    [[0.0, 0.0], [side, 0.0], [side, side], [0.0, side]]
+}
+
 fn marker_polygon() -> [[f64; 2]; 3] {
     [
         // Center of the hexagon